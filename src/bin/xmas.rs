@@ -0,0 +1,21 @@
+use std::{env, io, process};
+use advent_of_code_2020::day9::{report_invalid_values, PREAMBLE};
+
+fn main() {
+    let preamble = match env::args().nth(1) {
+        None => PREAMBLE,
+        Some(argument) => argument.parse().unwrap_or_else(|_| {
+            eprintln!("usage: xmas [PREAMBLE] < transmission");
+            process::exit(2)
+        })
+    };
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match report_invalid_values(stdin.lock(), stdout.lock(), preamble) {
+        Ok(count) => eprintln!("{} invalid values", count),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1)
+        }
+    }
+}
@@ -1,14 +1,18 @@
+use std::{collections::HashMap, fmt};
+use serde::Serialize;
+
+const TARGET: isize = 2020;
 
 fn recurse(input: &[isize], offset: usize, entries: &mut [isize], depth: usize) -> Option<isize> {
     (offset..input.len())
         .find_map(|offset| {
             
             let sum: isize = entries[..depth].iter().sum();
-            if sum > 2020 { return None }
+            if sum > TARGET { return None }
 
             if depth >= entries.len() {
                 return match sum {
-                    2020 => Some(entries[..depth].iter().product()),
+                    TARGET => Some(entries[..depth].iter().product()),
                     _ => None
                 };
             }
@@ -19,6 +23,118 @@ fn recurse(input: &[isize], offset: usize, entries: &mut [isize], depth: usize)
         })
 }
 
+fn collect_all(input: &[isize], offset: usize, entries: &mut Vec<isize>, size: usize, output: &mut Vec<Vec<isize>>) {
+    if entries.len() == size {
+        if entries.iter().sum::<isize>() == TARGET { output.push(entries.clone()) }
+        return;
+    }
+    (offset..input.len())
+        .for_each(|offset| {
+            entries.push(input[offset]);
+            collect_all(input, offset + 1, entries, size, output);
+            entries.pop();
+        });
+}
+
+fn find_all(input: &[isize], size: usize) -> Vec<Vec<isize>> {
+    let mut output = Vec::new();
+    collect_all(input, 0, &mut Vec::with_capacity(size), size, &mut output);
+    output.iter_mut().for_each(|entries| entries.sort());
+    output.sort();
+    output.dedup();
+    output
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct InvalidEntry {
+    pub line: usize,
+    pub content: String
+}
+
+pub fn parse_entries(input: &str) -> (Vec<isize>, Vec<InvalidEntry>) {
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    input.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .for_each(|(index, line)| match line.trim().parse::<isize>() {
+            Ok(value) => entries.push(value),
+            Err(_) => invalid.push(InvalidEntry { line: index + 1, content: line.to_owned() })
+        });
+    (entries, invalid)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ExpenseReport {
+    pub target: isize,
+    pub pairs: Vec<Vec<isize>>,
+    pub triples: Vec<Vec<isize>>,
+    pub duplicates: Vec<isize>,
+    pub unused: Vec<isize>,
+    pub invalid: Vec<InvalidEntry>
+}
+
+impl ExpenseReport {
+
+    pub fn from_input(input: &str) -> Self {
+        let (entries, invalid) = parse_entries(input);
+        let pairs = find_all(&entries, 2);
+        let triples = find_all(&entries, 3);
+
+        let mut counts: HashMap<isize, usize> = HashMap::new();
+        entries.iter().for_each(|&entry| *counts.entry(entry).or_insert(0) += 1);
+        let mut duplicates = counts.iter()
+            .filter(|(_, &count)| count > 1)
+            .map(|(&entry, _)| entry)
+            .collect::<Vec<_>>();
+        duplicates.sort();
+
+        let unused = entries.iter()
+            .filter(|&entry| !pairs.iter().chain(triples.iter()).any(|found| found.contains(entry)))
+            .copied()
+            .collect();
+
+        ExpenseReport { target: TARGET, pairs, triples, duplicates, unused, invalid }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+}
+
+fn write_list(f: &mut fmt::Formatter, values: &[isize]) -> fmt::Result {
+    match values {
+        [] => writeln!(f, " none"),
+        _ => {
+            values.iter().try_for_each(|value| write!(f, " {}", value))?;
+            writeln!(f)
+        }
+    }
+}
+
+impl fmt::Display for ExpenseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        for (name, found) in &[("pairs", &self.pairs), ("triples", &self.triples)] {
+            writeln!(f, "{} summing to {}: {}", name, self.target, found.len())?;
+            for entries in found.iter() {
+                let terms = entries.iter().map(ToString::to_string).collect::<Vec<_>>();
+                writeln!(f, "  {} (product {})", terms.join(" + "), entries.iter().product::<isize>())?;
+            }
+        }
+        write!(f, "duplicate entries:")?;
+        write_list(f, &self.duplicates)?;
+        write!(f, "unused entries:")?;
+        write_list(f, &self.unused)?;
+        writeln!(f, "unparsable lines: {}", self.invalid.len())?;
+        for entry in self.invalid.iter() {
+            writeln!(f, "  line {}: {:?}", entry.line, entry.content)?;
+        }
+        Ok(())
+    }
+}
+
 #[aoc_generator(day1)]
 pub fn input_generator(input: &str) -> Vec<isize> {
     input.lines()
@@ -36,6 +152,26 @@ pub fn solve_part_two(input: &[isize]) -> isize {
     recurse(input, 0, &mut [0isize; 3], 0).unwrap()
 }
 
+#[aoc_generator(day1, part1, Report)]
+pub fn report_generator(input: &str) -> ExpenseReport {
+    ExpenseReport::from_input(input)
+}
+
+#[aoc(day1, part1, Report)]
+pub fn report(input: &ExpenseReport) -> String {
+    input.to_string()
+}
+
+#[aoc_generator(day1, part1, Json)]
+pub fn json_generator(input: &str) -> ExpenseReport {
+    ExpenseReport::from_input(input)
+}
+
+#[aoc(day1, part1, Json)]
+pub fn report_json(input: &ExpenseReport) -> String {
+    input.to_json()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -62,4 +198,28 @@ mod test {
         assert_eq!(solve_part_two(&input), 241861950);
     }
 
+    #[test]
+    fn it_reports_anomalies() {
+        let report = ExpenseReport::from_input(indoc! {"
+            1721
+            979
+            366
+            abc
+            299
+            675
+            1456
+            1456
+            
+            12.5
+        "});
+        assert_eq!(report.pairs, vec![vec![299, 1721]]);
+        assert_eq!(report.triples, vec![vec![366, 675, 979]]);
+        assert_eq!(report.duplicates, vec![1456]);
+        assert_eq!(report.unused, vec![1456, 1456]);
+        assert_eq!(report.invalid, vec![
+            InvalidEntry { line: 4, content: "abc".to_owned() },
+            InvalidEntry { line: 10, content: "12.5".to_owned() },
+        ]);
+    }
+
 }
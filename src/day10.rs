@@ -1,4 +1,5 @@
-use std::{str::FromStr, collections::HashMap};
+use std::{str::FromStr, collections::BTreeMap, iter::Sum, ops::Sub};
+use num_bigint::BigUint;
 
 #[aoc_generator(day10)]
 pub fn input_generator(input: &str) -> Vec<usize> {
@@ -11,43 +12,197 @@ pub fn input_generator(input: &str) -> Vec<usize> {
     result
 }
 
-fn recurse(input: &[usize], (one, two, three): (usize, usize, usize)) -> Option<(usize, usize, usize)> {
-    if input.len() == 1 { return Some((one, two, three)) }
-    match input[1] - input[0] {
-        3 => recurse(&input[1..], (one, two, three + 1)),
-        2 => recurse(&input[1..], (one, two + 1, three)),
-        1 => recurse(&input[1..], (one + 1, two, three)),
-        0 => recurse(&input[1..], (one, two, three)),
-        _ => None
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Joltage {
+    pub tolerance: usize,
+    pub device_offset: usize
+}
+
+impl Joltage {
+    pub const PUZZLE: Joltage = Joltage { tolerance: 3, device_offset: 3 };
+
+    fn accepts(&self, from: usize, to: usize) -> bool {
+        to - from <= self.tolerance
     }
 }
 
+fn steps(input: &[usize], joltage: Joltage) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+    let device = input.last().map(|&last| (input.len() - 1, last, last + joltage.device_offset));
+    input.windows(2)
+        .enumerate()
+        .map(|(position, pair)| (position, pair[0], pair[1]))
+        .chain(device)
+}
+
+pub fn histogram(input: &[usize], joltage: Joltage) -> Option<Vec<usize>> {
+    let analysis = ChainAnalysis::new(input, joltage);
+    if !analysis.is_valid() { return None }
+    let mut histogram = vec![0; joltage.tolerance + 1];
+    analysis.distribution.iter()
+        .for_each(|(&difference, &count)| histogram[difference] = count);
+    Some(histogram)
+}
+
 #[aoc(day10, part1)]
 pub fn solve_part_one(input: &[usize]) -> usize {
-    match recurse(&input, (0, 0, 0)) {
-        Some((one, _, three)) => one * (three + 1),
+    match histogram(input, Joltage::PUZZLE) {
+        Some(histogram) => histogram[1] * histogram[3],
         None => panic!("Value not found")
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct Gap {
+    pub position: usize,
+    pub from: usize,
+    pub to: usize
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ChainAnalysis {
+    pub distribution: BTreeMap<usize, usize>,
+    pub gaps: Vec<Gap>
+}
+
+impl ChainAnalysis {
+
+    pub fn new(input: &[usize], joltage: Joltage) -> Self {
+        let mut distribution = BTreeMap::new();
+        let mut gaps = Vec::new();
+        for (position, from, to) in steps(input, joltage) {
+            *distribution.entry(to - from).or_insert(0) += 1;
+            if !joltage.accepts(from, to) {
+                gaps.push(Gap { position, from, to });
+            }
+        }
+        ChainAnalysis { distribution, gaps }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.gaps.is_empty()
+    }
+
+}
+
+pub fn count_ways<T: Clone + From<u8> + Sum<T>>(input: &[usize], joltage: Joltage) -> Vec<T> {
+    let mut ways = vec![T::from(0); input.len()];
+    for index in (0..input.len()).rev() {
+        ways[index] = match index == input.len() - 1 {
+            true => T::from((joltage.device_offset <= joltage.tolerance) as u8),
+            false => (index + 1..input.len())
+                .take_while(|&next| joltage.accepts(input[index], input[next]))
+                .map(|next| ways[next].clone())
+                .sum()
+        };
+    }
+    ways
+}
+
+fn can_reach_device(input: &[usize], joltage: Joltage) -> Vec<bool> {
+    let mut reachable = vec![false; input.len()];
+    for index in (0..input.len()).rev() {
+        reachable[index] = match index == input.len() - 1 {
+            true => joltage.device_offset <= joltage.tolerance,
+            false => (index + 1..input.len())
+                .take_while(|&next| joltage.accepts(input[index], input[next]))
+                .any(|next| reachable[next])
+        };
+    }
+    reachable
+}
+
+pub struct Arrangements<'a> {
+    input: &'a [usize],
+    joltage: Joltage,
+    reachable: Vec<bool>,
+    path: Vec<usize>,
+    extending: bool
+}
+
+impl <'a> Arrangements<'a> {
+    fn next_step(&self, from: usize, after: usize) -> Option<usize> {
+        (after + 1..self.input.len())
+            .take_while(|&next| self.joltage.accepts(self.input[from], self.input[next]))
+            .find(|&next| self.reachable[next])
+    }
+}
+
+impl <'a> Iterator for Arrangements<'a> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.extending {
+                let last = *self.path.last()?;
+                if last == self.input.len() - 1 {
+                    self.extending = false;
+                    return Some(self.path.iter().map(|&index| self.input[index]).collect());
+                }
+                match self.next_step(last, last) {
+                    Some(next) => self.path.push(next),
+                    None => self.extending = false
+                }
+            } else {
+                let last = self.path.pop()?;
+                let previous = *self.path.last()?;
+                if let Some(next) = self.next_step(previous, last) {
+                    self.path.push(next);
+                    self.extending = true;
+                }
+            }
+        }
+    }
+}
+
+pub fn arrangements(input: &[usize], joltage: Joltage) -> Arrangements<'_> {
+    let reachable = can_reach_device(input, joltage);
+    let path = match reachable.first() {
+        Some(true) => vec![0],
+        _ => vec![]
+    };
+    Arrangements { input, joltage, reachable, path, extending: true }
+}
+
+pub fn nth_arrangement<T>(input: &[usize], ways: &[T], mut index: T, joltage: Joltage) -> Option<Vec<usize>>
+where T: Clone + PartialOrd + Sub<Output = T> {
+    if input.is_empty() || index >= ways[0] { return None }
+    let mut current = 0;
+    let mut arrangement = vec![input[0]];
+    while current < input.len() - 1 {
+        let candidates = (current + 1..input.len())
+            .take_while(|&next| joltage.accepts(input[current], input[next]));
+        for next in candidates {
+            match index < ways[next] {
+                true => { current = next; break },
+                false => index = index - ways[next].clone()
+            }
+        }
+        arrangement.push(input[current]);
+    }
+    Some(arrangement)
+}
+
+pub fn sample_arrangements(input: &[usize], count: usize, joltage: Joltage) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let ways = count_ways::<BigUint>(input, joltage);
+    let total = ways.first().cloned().unwrap_or_default();
+    let step = match &total / BigUint::from(count.max(1)) {
+        step if step == BigUint::from(0u8) => BigUint::from(1u8),
+        step => step
+    };
+    (0..count)
+        .map(move |sample| BigUint::from(sample) * &step)
+        .take_while(move |index| *index < total)
+        .filter_map(move |index| nth_arrangement(input, &ways, index, joltage))
+}
+
 #[aoc(day10, part2)]
 pub fn solve_part_two(input: &[usize]) -> usize {
-    let mut hashmap: HashMap<usize, usize> = HashMap::new();
-    let last = input.iter().last().unwrap();
-    hashmap.insert(last + 3, 1);
-    input.iter()
-        .rev()
-        .fold(&mut hashmap, |acc, item| {
-            let sum = (1..=3)
-                .map(|i| item + i)
-                .filter_map(|i| acc.get(&i))
-                .sum();
-            acc.insert(*item, sum);
-            acc
-        })
-        .get(&0)
-        .unwrap()
-        .clone()
+    count_ways(input, Joltage::PUZZLE)[0]
+}
+
+#[aoc(day10, part2, BigUint)]
+pub fn solve_part_two_big(input: &[usize]) -> BigUint {
+    count_ways::<BigUint>(input, Joltage::PUZZLE).swap_remove(0)
 }
 
 #[cfg(test)]
@@ -119,4 +274,97 @@ mod test {
         assert_eq!(solve_part_two(&input_two), 19208);
     }
 
+    #[test]
+    fn it_stops_early_on_unreachable_devices() {
+        let mut input = (0..2000).collect::<Vec<_>>();
+        input.push(2010);
+        assert_eq!(arrangements(&input, Joltage::PUZZLE).next(), None);
+
+        let input = (0..30).collect::<Vec<_>>();
+        assert_eq!(arrangements(&input, Joltage { tolerance: 3, device_offset: 4 }).next(), None);
+
+        let input = input_generator("1\n5\n6\n");
+        assert_eq!(arrangements(&input, Joltage { tolerance: 5, device_offset: 3 }).collect::<Vec<_>>(), vec![
+            vec![0, 1, 5, 6], vec![0, 1, 6], vec![0, 5, 6]
+        ]);
+    }
+
+    #[test]
+    fn it_supports_other_tolerances() {
+        let input = input_generator(INPUT_ONE);
+        assert_eq!(histogram(&input, Joltage::PUZZLE), Some(vec![0, 7, 0, 5]));
+        assert_eq!(histogram(&input, Joltage { tolerance: 2, device_offset: 1 }), None);
+        assert_eq!(histogram(&input, Joltage { tolerance: 4, device_offset: 4 }), Some(vec![0, 7, 0, 4, 1]));
+        let analysis = ChainAnalysis::new(&input, Joltage::PUZZLE);
+        let expected = histogram(&input, Joltage::PUZZLE).unwrap();
+        assert!(analysis.distribution.iter().all(|(&difference, &count)| expected[difference] == count));
+
+        let loose = Joltage { tolerance: 4, device_offset: 3 };
+        assert_eq!(count_ways::<usize>(&input, loose)[0], arrangements(&input, loose).count());
+        assert!(count_ways::<usize>(&input, loose)[0] > 8);
+        let unreachable = Joltage { tolerance: 3, device_offset: 4 };
+        assert_eq!(count_ways::<usize>(&input, unreachable)[0], 0);
+        assert_eq!(arrangements(&input, unreachable).next(), None);
+        assert_eq!(ChainAnalysis::new(&input, unreachable).gaps, vec![Gap { position: 11, from: 19, to: 23 }]);
+    }
+
+    #[test]
+    fn it_counts_long_chains_exactly() {
+        let input = (0..=300).collect::<Vec<_>>();
+        assert_eq!(solve_part_two_big(&input_generator(INPUT_TWO)), BigUint::from(19208u32));
+        let count = count_ways::<BigUint>(&input, Joltage::PUZZLE).swap_remove(0);
+        assert!(count > BigUint::from(u128::MAX));
+        assert_eq!(
+            count_ways::<BigUint>(&input[..80], Joltage::PUZZLE)[0],
+            BigUint::from(count_ways::<u128>(&input[..80], Joltage::PUZZLE)[0])
+        );
+
+        let samples = sample_arrangements(&input, 3, Joltage::PUZZLE).collect::<Vec<_>>();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0], input);
+        assert!(samples.iter().all(|sample| {
+            sample.first() == Some(&0) && sample.last() == Some(&300) && sample.windows(2).all(|pair| pair[1] - pair[0] <= 3)
+        }));
+        assert_ne!(samples[1], samples[2]);
+        let ways = count_ways::<BigUint>(&input, Joltage::PUZZLE);
+        assert_eq!(nth_arrangement(&input, &ways, count.clone(), Joltage::PUZZLE), None);
+        assert!(nth_arrangement(&input, &ways, count - BigUint::from(1u8), Joltage::PUZZLE).is_some());
+    }
+
+    #[test]
+    fn it_analyzes_chains() {
+        let analysis = ChainAnalysis::new(&input_generator(INPUT_TWO), Joltage::PUZZLE);
+        assert_eq!(analysis.distribution, vec![(1, 22), (3, 10)].into_iter().collect());
+        assert!(analysis.is_valid());
+
+        let analysis = ChainAnalysis::new(&input_generator("1\n2\n7\n8\n13\n"), Joltage::PUZZLE);
+        assert_eq!(analysis.gaps, vec![
+            Gap { position: 2, from: 2, to: 7 },
+            Gap { position: 4, from: 8, to: 13 }
+        ]);
+        assert!(!analysis.is_valid());
+    }
+
+    #[test]
+    fn it_enumerates_arrangements() {
+        let input = input_generator(INPUT_ONE);
+        let all = arrangements(&input, Joltage::PUZZLE).collect::<Vec<_>>();
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], input);
+        assert_eq!(all[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19]);
+        assert_eq!(all.iter().collect::<std::collections::HashSet<_>>().len(), 8);
+
+        let ways = count_ways(&input, Joltage::PUZZLE);
+        assert!((0..8).all(|index| nth_arrangement(&input, &ways, index, Joltage::PUZZLE).as_ref() == Some(&all[index])));
+        assert_eq!(nth_arrangement(&input, &ways, 8, Joltage::PUZZLE), None);
+        assert_eq!(sample_arrangements(&input, 4, Joltage::PUZZLE).collect::<Vec<_>>(), vec![
+            all[0].clone(), all[2].clone(), all[4].clone(), all[6].clone()
+        ]);
+
+        let input = input_generator(INPUT_TWO);
+        assert_eq!(arrangements(&input, Joltage::PUZZLE).take(1000).count(), 1000);
+        assert_eq!(sample_arrangements(&input, 3, Joltage::PUZZLE).count(), 3);
+        assert_eq!(arrangements(&input_generator("1\n5\n"), Joltage::PUZZLE).next(), None);
+    }
+
 }
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, collections::{HashMap, HashSet}, iter::repeat};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, iter::repeat, mem::swap};
 
 type Position = (isize, isize);
 type Grid = HashMap<Position, Space>;
@@ -24,13 +24,17 @@ impl Space {
 const DIRECTIONS: [[isize;2]; 8] = [[-1, -1], [-1, 0], [-1, 1], [0, -1], [0, 1], [1, -1], [1, 0], [1, 1]]; 
 
 trait Heuristic {
+    const THRESHOLD: usize;
     fn will_change(grid: &Grid, pos: &Position) -> bool;
     fn affected_by(grid: &Grid, pos: &Position) -> HashSet<Position>;
+    fn seen(grid: &DenseGrid) -> Vec<Vec<usize>>;
 }
 
 struct Neighbors {}
 
 impl Heuristic for Neighbors {
+    const THRESHOLD: usize = 4;
+
     fn will_change(grid: &Grid, pos: &Position) -> bool {
         let occupied_adjecent = Self::affected_by(grid, pos)
             .iter()
@@ -38,7 +42,7 @@ impl Heuristic for Neighbors {
             .filter(|&space| *space == Space::Occupied)
             .count();
         match grid.get(&pos) {
-            Some(Space::Occupied) => occupied_adjecent >= 4,
+            Some(Space::Occupied) => occupied_adjecent >= Self::THRESHOLD,
             Some(Space::Empty) => occupied_adjecent == 0,
             Some(Space::Floor) => false,
             None => false
@@ -52,11 +56,17 @@ impl Heuristic for Neighbors {
             .filter(|next| grid.contains_key(next))
             .collect()
     }
+
+    fn seen(grid: &DenseGrid) -> Vec<Vec<usize>> {
+        grid.seen_by(|row, column, [x, y]| grid.seat_at(row + x, column + y))
+    }
 }
 
 struct LineOfSight {}
 
 impl Heuristic for LineOfSight {
+    const THRESHOLD: usize = 5;
+
     fn will_change(grid: &Grid, pos: &Position) -> bool {
         let occupied_adjecent = Self::affected_by(grid, pos)
             .iter()
@@ -64,7 +74,7 @@ impl Heuristic for LineOfSight {
             .filter(|&space| *space == Space::Occupied)
             .count();
         match grid.get(&pos) {
-            Some(Space::Occupied) => occupied_adjecent >= 5,
+            Some(Space::Occupied) => occupied_adjecent >= Self::THRESHOLD,
             Some(Space::Empty) => occupied_adjecent == 0,
             Some(Space::Floor) => false,
             None => false
@@ -90,6 +100,90 @@ impl Heuristic for LineOfSight {
             })
             .collect()
     }
+
+    fn seen(grid: &DenseGrid) -> Vec<Vec<usize>> {
+        grid.seen_by(|row, column, [x, y]| {
+            (1..)
+                .map(|distance| (row + x * distance, column + y * distance))
+                .take_while(|&(row, column)| grid.contains(row, column))
+                .find_map(|(row, column)| grid.seat_at(row, column))
+        })
+    }
+}
+
+pub struct DenseGrid {
+    width: usize,
+    height: usize,
+    spaces: Vec<Space>
+}
+
+impl DenseGrid {
+
+    pub fn from_input(input: &str) -> Self {
+        let rows = input.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let width = rows.first().map_or(0, |row| row.len());
+        let spaces = rows.iter()
+            .flat_map(|row| row.chars().map(Space::from_char))
+            .collect::<Vec<_>>();
+        assert_eq!(spaces.len(), width * rows.len(), "Rows must all be the same width");
+        DenseGrid { width, height: rows.len(), spaces }
+    }
+
+    fn contains(&self, row: isize, column: isize) -> bool {
+        row >= 0 && column >= 0 && (row as usize) < self.height && (column as usize) < self.width
+    }
+
+    fn seat_at(&self, row: isize, column: isize) -> Option<usize> {
+        if !self.contains(row, column) { return None }
+        let index = row as usize * self.width + column as usize;
+        match self.spaces[index] {
+            Space::Floor => None,
+            _ => Some(index)
+        }
+    }
+
+    fn seen_by<F: Fn(isize, isize, [isize; 2]) -> Option<usize>>(&self, find: F) -> Vec<Vec<usize>> {
+        (0..self.spaces.len())
+            .map(|index| match self.spaces[index] {
+                Space::Floor => Vec::new(),
+                _ => {
+                    let (row, column) = ((index / self.width) as isize, (index % self.width) as isize);
+                    DIRECTIONS.iter()
+                        .filter_map(|&direction| find(row, column, direction))
+                        .collect()
+                }
+            })
+            .collect()
+    }
+
+}
+
+fn solve_dense<H: Heuristic>(input: &DenseGrid) -> usize {
+    let seen = H::seen(input);
+    let mut current = input.spaces.clone();
+    let mut next = current.clone();
+    loop {
+        let mut changed = false;
+        for (index, &space) in current.iter().enumerate() {
+            let occupied = seen[index].iter()
+                .filter(|&&other| current[other] == Space::Occupied)
+                .count();
+            next[index] = match space {
+                Space::Empty if occupied == 0 => Space::Occupied,
+                Space::Occupied if occupied >= H::THRESHOLD => Space::Empty,
+                space => space
+            };
+            changed |= next[index] != space;
+        }
+        swap(&mut current, &mut next);
+        if !changed { break }
+    }
+    current.iter()
+        .filter(|&&space| space == Space::Occupied)
+        .count()
 }
 
 fn get_changes<H: Heuristic>(grid: &Grid, prev_changes: &HashSet<Position>) -> HashSet<Position> {
@@ -153,6 +247,22 @@ pub fn solve_part_two(input: &Grid) -> usize {
     solve::<LineOfSight>(input)
 }
 
+#[aoc_generator(day11, part1, Dense)]
+#[aoc_generator(day11, part2, Dense)]
+pub fn dense_generator(input: &str) -> DenseGrid {
+    DenseGrid::from_input(input)
+}
+
+#[aoc(day11, part1, Dense)]
+pub fn solve_part_one_dense(input: &DenseGrid) -> usize {
+    solve_dense::<Neighbors>(input)
+}
+
+#[aoc(day11, part2, Dense)]
+pub fn solve_part_two_dense(input: &DenseGrid) -> usize {
+    solve_dense::<LineOfSight>(input)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -183,4 +293,24 @@ mod test {
         assert_eq!(solve_part_two(&input), 26);
     }
 
+    #[test]
+    fn it_solves_with_a_dense_grid() {
+        let input = dense_generator(INPUT);
+        assert_eq!(solve_part_one_dense(&input), 37);
+        assert_eq!(solve_part_two_dense(&input), 26);
+
+        let input = dense_generator(indoc! {"
+            .##.##.
+            #.#.#.#
+            ##...##
+            ...L...
+            ##...##
+            #.#.#.#
+            .##.##.
+        "});
+        assert_eq!(LineOfSight::seen(&input)[3 * 7 + 3], Vec::<usize>::new());
+        assert_eq!(Neighbors::seen(&input)[0], Vec::<usize>::new());
+        assert_eq!(Neighbors::seen(&input)[1], vec![2, 7, 9]);
+    }
+
 }
@@ -1,69 +1,569 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use nom::{IResult, bytes::complete::tag, bytes::complete::take, character::complete::digit1, character::complete::space1, combinator::map_res, sequence::tuple};
+use nom::{IResult, branch::alt, bytes::complete::tag, error::ErrorKind, character::complete::alpha1, character::complete::digit1, character::complete::not_line_ending, character::complete::space1, combinator::all_consuming, combinator::map, combinator::map_res, sequence::preceded, sequence::tuple};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
+pub trait PasswordPolicy: fmt::Display {
+    fn is_valid(&self, password: &str) -> bool;
+    fn explain(&self, password: &str) -> Option<String>;
+}
+
+fn graphemes_at(password: &str, first: usize, second: usize) -> (Option<&str>, Option<&str>) {
+    let mut found = (None, None);
+    for (index, grapheme) in password.graphemes(true).enumerate().take(first.max(second)) {
+        if index + 1 == first { found.0 = Some(grapheme) }
+        if index + 1 == second { found.1 = Some(grapheme) }
+    }
+    found
+}
+
+pub struct CharacterCount<'a> {
+    pub character: &'a str,
+    pub min: usize,
+    pub max: usize
+}
+
+impl <'a> CharacterCount<'a> {
+    pub fn count(&self, password: &str) -> usize {
+        password.graphemes(true).filter(|&g| g == self.character).count()
+    }
+}
+
+impl <'a> PasswordPolicy for CharacterCount<'a> {
+    fn is_valid(&self, password: &str) -> bool {
+        let count = self.count(password);
+        count >= self.min && count <= self.max
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        let count = self.count(password);
+        if count >= self.min && count <= self.max { return None }
+        Some(format!("expected {} to {} of {:?} but found {}", self.min, self.max, self.character, count))
+    }
+}
+
+impl <'a> fmt::Display for CharacterCount<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "count {}-{} {}", self.min, self.max, self.character)
+    }
+}
+
+pub struct CharacterPosition<'a> {
+    pub character: &'a str,
+    pub first: usize,
+    pub second: usize
+}
+
+impl <'a> PasswordPolicy for CharacterPosition<'a> {
+    fn is_valid(&self, password: &str) -> bool {
+        match graphemes_at(password, self.first, self.second) {
+            (Some(first), Some(second)) => (first == self.character || second == self.character) && first != second,
+            (Some(first), None) => first == self.character,
+            _ => false
+        }
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.is_valid(password) { return None }
+        let missing = |position| format!(
+            "position {} does not exist in the {}-character password", position, password.graphemes(true).count()
+        );
+        let reason = match graphemes_at(password, self.first, self.second) {
+            (Some(first), Some(second)) if first == self.character && second == self.character =>
+                format!("positions {} and {} are both {:?}", self.first, self.second, self.character),
+            (Some(first), Some(second)) =>
+                format!("neither position {} ({:?}) nor position {} ({:?}) is {:?}", self.first, first, self.second, second, self.character),
+            (Some(first), None) =>
+                format!("position {} is {:?} not {:?}, and {}", self.first, first, self.character, missing(self.second)),
+            (None, _) => missing(self.first)
+        };
+        Some(reason)
+    }
+}
+
+impl <'a> fmt::Display for CharacterPosition<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "position {}-{} {}", self.first, self.second, self.character)
+    }
+}
+
+pub struct MinimumLength(pub usize);
+
+impl PasswordPolicy for MinimumLength {
+    fn is_valid(&self, password: &str) -> bool {
+        password.graphemes(true).count() >= self.0
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        let length = password.graphemes(true).count();
+        if length >= self.0 { return None }
+        Some(format!("expected at least {} characters but found {}", self.0, length))
+    }
+}
+
+impl fmt::Display for MinimumLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "min-length {}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol
+}
+
+impl CharacterClass {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharacterClass::Lowercase => c.is_lowercase(),
+            CharacterClass::Uppercase => c.is_uppercase(),
+            CharacterClass::Digit => c.is_numeric(),
+            CharacterClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace()
+        }
+    }
+}
+
+impl FromStr for CharacterClass {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "lowercase" => Ok(CharacterClass::Lowercase),
+            "uppercase" => Ok(CharacterClass::Uppercase),
+            "digit" => Ok(CharacterClass::Digit),
+            "symbol" => Ok(CharacterClass::Symbol),
+            _ => Err(format!("unknown character class {:?}", input))
+        }
+    }
+}
+
+impl fmt::Display for CharacterClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CharacterClass::Lowercase => "lowercase",
+            CharacterClass::Uppercase => "uppercase",
+            CharacterClass::Digit => "digit",
+            CharacterClass::Symbol => "symbol"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct RequiresClass(pub CharacterClass);
+
+impl PasswordPolicy for RequiresClass {
+    fn is_valid(&self, password: &str) -> bool {
+        password.chars().any(|c| self.0.contains(c))
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.is_valid(password) { return None }
+        Some(format!("expected at least one {} character", self.0))
+    }
+}
+
+impl fmt::Display for RequiresClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "requires {}", self.0)
+    }
+}
+
+pub struct ForbiddenSubstring<'a>(pub &'a str);
+
+impl <'a> PasswordPolicy for ForbiddenSubstring<'a> {
+    fn is_valid(&self, password: &str) -> bool {
+        !password.to_lowercase().contains(&self.0.to_lowercase())
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.is_valid(password) { return None }
+        Some(format!("contains forbidden substring {:?}", self.0))
+    }
+}
+
+impl <'a> fmt::Display for ForbiddenSubstring<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "forbids {}", self.0)
+    }
+}
+
+pub struct Pattern(pub Regex);
+
+impl PasswordPolicy for Pattern {
+    fn is_valid(&self, password: &str) -> bool {
+        self.0.is_match(password)
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.is_valid(password) { return None }
+        Some(format!("does not match /{}/", self.0))
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "matches {}", self.0)
+    }
+}
+
+fn parse_range(input: &str) -> IResult<&str, (usize, usize)> {
+    let (rest, (first, _, second)) = tuple((
+        map_res(digit1, FromStr::from_str),
+        tag("-"),
+        map_res(digit1, FromStr::from_str),
+    ))(input)?;
+    Ok((rest, (first, second)))
+}
+
+fn parse_character(input: &str) -> IResult<&str, &str> {
+    match input.graphemes(true).next() {
+        Some(grapheme) if !grapheme.trim().is_empty() => Ok((&input[grapheme.len()..], grapheme)),
+        _ => Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Char)))
+    }
+}
+
+type BoxedPolicy<'a> = Box<dyn PasswordPolicy + 'a>;
+
+fn parse_policy(input: &str) -> IResult<&str, BoxedPolicy<'_>> {
+    alt((
+        map(
+            tuple((tag("count"), space1, parse_range, space1, parse_character)),
+            |(_, _, (min, max), _, character)| Box::new(CharacterCount { character, min, max }) as BoxedPolicy
+        ),
+        map(
+            tuple((tag("position"), space1, parse_range, space1, parse_character)),
+            |(_, _, (first, second), _, character)| Box::new(CharacterPosition { character, first, second }) as BoxedPolicy
+        ),
+        map(
+            preceded(tuple((tag("min-length"), space1)), map_res(digit1, FromStr::from_str)),
+            |length| Box::new(MinimumLength(length)) as BoxedPolicy
+        ),
+        map(
+            preceded(tuple((tag("requires"), space1)), map_res(alpha1, FromStr::from_str)),
+            |class| Box::new(RequiresClass(class)) as BoxedPolicy
+        ),
+        map(
+            preceded(tuple((tag("forbids"), space1)), not_line_ending),
+            |substring| Box::new(ForbiddenSubstring(substring)) as BoxedPolicy
+        ),
+        map(
+            preceded(tuple((tag("matches"), space1)), map_res(not_line_ending, Regex::new)),
+            |regex| Box::new(Pattern(regex)) as BoxedPolicy
+        ),
+    ))(input)
+}
+
+pub struct PolicySet<'a> {
+    policies: Vec<BoxedPolicy<'a>>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub policy: String,
+    pub reason: String
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Violation<'a> {
+    pub line: usize,
+    pub password: &'a str,
+    pub failures: Vec<Failure>
+}
+
+impl <'a> PolicySet<'a> {
+
+    pub fn new(policies: Vec<BoxedPolicy<'a>>) -> Self {
+        PolicySet { policies }
+    }
 
-pub struct Rule {
+    pub fn from_spec(input: &'a str) -> Result<Self, String> {
+        input.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                all_consuming(parse_policy)(line.trim())
+                    .map(|(_, policy)| policy)
+                    .map_err(|_| format!("line {}: invalid policy {:?}", index + 1, line))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(PolicySet::new)
+    }
+
+    pub fn is_valid(&self, password: &str) -> bool {
+        self.policies.iter().all(|policy| policy.is_valid(password))
+    }
+
+    pub fn validate<'b>(&self, passwords: &'b str) -> Vec<Violation<'b>> {
+        passwords.lines()
+            .enumerate()
+            .filter_map(|(index, password)| {
+                let failures = self.policies.iter()
+                    .filter_map(|policy| {
+                        let reason = policy.explain(password)?;
+                        Some(Failure { policy: policy.to_string(), reason })
+                    })
+                    .collect::<Vec<_>>();
+                match failures.is_empty() {
+                    true => None,
+                    false => Some(Violation { line: index + 1, password, failures })
+                }
+            })
+            .collect()
+    }
+
+}
+
+pub struct Rule<'a> {
     first: usize,
     second: usize,
-    character: char,
-    password: String
+    character: &'a str,
+    password: &'a str
 }
 
-impl Rule {
+impl <'a> Rule<'a> {
 
-    pub fn from_line(input: &str) -> Self {
-        let result: IResult<&str, (usize, &str, usize, &str, char,  &str, &str)> = tuple((
-            map_res(digit1, FromStr::from_str),
-            tag("-"),
-            map_res(digit1, FromStr::from_str),
+    pub fn from_line(input: &'a str) -> Result<Self, String> {
+        let result: IResult<&str, _> = tuple((
+            parse_range,
             space1,
-            map_res(take(1u8), |s: &str| { s.chars().next().ok_or(nom::Err::Failure("Empty")) }),
+            parse_character,
             tag(":"),
             space1,
         ))(input);
-        let (password, (first, _, second, _, character, _, _)) = result.unwrap();
-        Rule { first, second, character, password: password.to_owned() }
+        match result {
+            Ok((password, ((first, second), _, character, _, _))) => Ok(Rule { first, second, character, password }),
+            Err(_) => Err(format!("{:?} is not a valid rule", input))
+        }
+    }
+
+    pub fn count_policy(&self) -> CharacterCount<'a> {
+        CharacterCount { character: self.character, min: self.first, max: self.second }
+    }
+
+    pub fn position_policy(&self) -> CharacterPosition<'a> {
+        CharacterPosition { character: self.character, first: self.first, second: self.second }
     }
 
     pub fn has_proper_character_count(&self) -> bool {
-        let count = self.password.chars().filter(|c| *c == self.character).count();
-        count >= self.first && count <= self.second
+        self.count_policy().is_valid(self.password)
     }
 
     pub fn has_proper_character_position(&self) -> bool {
-        let first = self.password.chars().skip(self.first - 1).next();
-        let second = self.password.chars().skip(self.second - 1).next();
-        match (first, second) {
-            (Some(first), Some(second)) => (first == self.character || second == self.character) && first != second,
-            (Some(first), None) => first == self.character,
-            _ => false
-        }
+        self.position_policy().is_valid(self.password)
+    }
+
+    pub fn explain_character_count(&self) -> Option<String> {
+        self.count_policy().explain(self.password)
+    }
+
+    pub fn explain_character_position(&self) -> Option<String> {
+        self.position_policy().explain(self.password)
     }
 
 }
 
-#[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<Rule> {
+pub fn input_generator(input: &str) -> impl Iterator<Item = Result<Rule<'_>, String>> {
     input.lines()
         .map(Rule::from_line)
-        .collect()
+}
+
+fn rules(input: &str) -> impl Iterator<Item = Rule<'_>> {
+    input_generator(input)
+        .map(|rule| rule.unwrap_or_else(|error| panic!("{}", error)))
 }
 
 #[aoc(day2, part1)]
-pub fn solve_part_one(input: &[Rule]) -> usize {
-    input.iter()
+pub fn solve_part_one(input: &str) -> usize {
+    rules(input)
         .filter(|i| i.has_proper_character_count())
         .count()
 }
 
 #[aoc(day2, part2)]
-pub fn solve_part_two(input: &[Rule]) -> usize {
-    input.iter()
+pub fn solve_part_two(input: &str) -> usize {
+    rules(input)
         .filter(|i| i.has_proper_character_position())
         .count()
 }
 
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RuleKind {
+    Count,
+    Position
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RuleViolation<'a> {
+    pub line: usize,
+    pub policy: String,
+    pub password: &'a str,
+    pub expected: String,
+    pub observed: String,
+    pub reason: String
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CharacterStats<'a> {
+    pub character: &'a str,
+    pub rules: usize,
+    pub failures: usize
+}
+
+pub struct ViolationReport<'a> {
+    pub kind: RuleKind,
+    pub total: usize,
+    pub violations: Vec<RuleViolation<'a>>,
+    pub malformed: Vec<(usize, String)>,
+    pub characters: Vec<CharacterStats<'a>>
+}
+
+fn describe(grapheme: Option<&str>) -> String {
+    match grapheme {
+        Some(grapheme) => grapheme.to_owned(),
+        None => "-".to_owned()
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains(&[',', '"', '\n', '\r'][..]) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned()
+    }
+}
+
+impl <'a> Rule<'a> {
+
+    fn violation(&self, line: usize, kind: RuleKind) -> Option<RuleViolation<'a>> {
+        let (policy, reason, expected, observed) = match kind {
+            RuleKind::Count => {
+                let policy = self.count_policy();
+                let reason = policy.explain(self.password)?;
+                let expected = format!("{}-{}", self.first, self.second);
+                (policy.to_string(), reason, expected, policy.count(self.password).to_string())
+            }
+            RuleKind::Position => {
+                let policy = self.position_policy();
+                let reason = policy.explain(self.password)?;
+                let expected = format!("exactly one of {} and {}", self.first, self.second);
+                let (first, second) = graphemes_at(self.password, self.first, self.second);
+                (policy.to_string(), reason, expected, format!("{} {}", describe(first), describe(second)))
+            }
+        };
+        Some(RuleViolation { line, policy, password: self.password, expected, observed, reason })
+    }
+
+}
+
+impl <'a> ViolationReport<'a> {
+
+    pub fn new(input: &'a str, kind: RuleKind) -> Self {
+        let mut total = 0;
+        let mut violations = Vec::new();
+        let mut malformed = Vec::new();
+        let mut characters: Vec<CharacterStats> = Vec::new();
+        for (index, rule) in input_generator(input).enumerate() {
+            total += 1;
+            let rule = match rule {
+                Ok(rule) => rule,
+                Err(error) => {
+                    malformed.push((index + 1, error));
+                    continue
+                }
+            };
+            let violation = rule.violation(index + 1, kind);
+            let stats = match characters.iter().position(|stats| stats.character == rule.character) {
+                Some(position) => &mut characters[position],
+                None => {
+                    characters.push(CharacterStats { character: rule.character, rules: 0, failures: 0 });
+                    characters.last_mut().unwrap()
+                }
+            };
+            stats.rules += 1;
+            if let Some(violation) = violation {
+                stats.failures += 1;
+                violations.push(violation);
+            }
+        }
+        characters.sort_by(|a, b| a.character.cmp(b.character));
+        ViolationReport { kind, total, violations, malformed, characters }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = String::from("line,policy,password,expected,observed,reason\n");
+        self.violations.iter()
+            .for_each(|v| {
+                let fields = [v.line.to_string(), v.policy.clone(), v.password.to_owned(), v.expected.clone(), v.observed.clone(), v.reason.clone()];
+                let fields = fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>();
+                output.push_str(&fields.join(","));
+                output.push('\n');
+            });
+        self.malformed.iter()
+            .for_each(|(line, error)| output.push_str(&format!("{},,,,,{}\n", line, csv_field(error))));
+        output
+    }
+
+    pub fn summary_to_csv(&self) -> String {
+        let mut output = String::from("character,rules,failures\n");
+        self.characters.iter()
+            .for_each(|stats| output.push_str(&format!("{},{},{}\n", csv_field(stats.character), stats.rules, stats.failures)));
+        output
+    }
+
+}
+
+impl <'a> fmt::Display for ViolationReport<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln!(f, "{} of {} passwords violate the {:?} policy", self.violations.len(), self.total, self.kind)?;
+        for v in self.violations.iter() {
+            writeln!(f, "  line {}: {:?} fails `{}`: {}", v.line, v.password, v.policy, v.reason)?;
+        }
+        for (line, error) in self.malformed.iter() {
+            writeln!(f, "  line {}: {}", line, error)?;
+        }
+        writeln!(f, "per character:")?;
+        for stats in self.characters.iter() {
+            let rate = 100.0 * stats.failures as f64 / stats.rules as f64;
+            writeln!(f, "  {}: {} of {} failed ({:.1}%)", stats.character, stats.failures, stats.rules, rate)?;
+        }
+        Ok(())
+    }
+}
+
+#[aoc(day2, part1, Report)]
+pub fn report_part_one(input: &str) -> String {
+    ViolationReport::new(input, RuleKind::Count).to_string()
+}
+
+#[aoc(day2, part1, Csv)]
+pub fn csv_part_one(input: &str) -> String {
+    format!("\n{}", ViolationReport::new(input, RuleKind::Count).to_csv())
+}
+
+#[aoc(day2, part1, SummaryCsv)]
+pub fn summary_csv_part_one(input: &str) -> String {
+    format!("\n{}", ViolationReport::new(input, RuleKind::Count).summary_to_csv())
+}
+
+#[aoc(day2, part2, Report)]
+pub fn report_part_two(input: &str) -> String {
+    ViolationReport::new(input, RuleKind::Position).to_string()
+}
+
+#[aoc(day2, part2, Csv)]
+pub fn csv_part_two(input: &str) -> String {
+    format!("\n{}", ViolationReport::new(input, RuleKind::Position).to_csv())
+}
+
+#[aoc(day2, part2, SummaryCsv)]
+pub fn summary_csv_part_two(input: &str) -> String {
+    format!("\n{}", ViolationReport::new(input, RuleKind::Position).summary_to_csv())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -75,16 +575,124 @@ mod test {
         2-9 c: ccccccccc
     "};
 
+    const POLICIES: &str = indoc! {"
+        # corporate password policy
+        min-length 8
+        requires digit
+        requires uppercase
+        forbids password
+        matches ^[[:ascii:]]+$
+    "};
+
+    const PASSWORDS: &str = indoc! {"
+        Tr0ub4dor
+        hunter2
+        MyPassword1
+        CorrectHorse9
+        Ünïcödé99Ab
+    "};
+
     #[test]
     fn it_solves_part_one() {
-        let input = input_generator(INPUT);
-        assert_eq!(solve_part_one(&input), 2);
+        assert_eq!(solve_part_one(INPUT), 2);
     }
-    
+
     #[test]
     fn it_solves_part_two() {
-        let input = input_generator(INPUT);
-        assert_eq!(solve_part_two(&input), 1);
+        assert_eq!(solve_part_two(INPUT), 1);
+    }
+
+    #[test]
+    fn it_handles_graphemes() {
+        let rule = Rule::from_line("1-3 e\u{301}: e\u{301}xe\u{301}").unwrap();
+        assert!(rule.has_proper_character_count());
+        assert!(!rule.has_proper_character_position());
+        let rule = Rule::from_line("2-3 ü: aüb").unwrap();
+        assert!(!rule.has_proper_character_count());
+        assert!(rule.has_proper_character_position());
+    }
+
+    #[test]
+    fn it_explains_failures() {
+        let rules = rules(INPUT).collect::<Vec<_>>();
+        assert_eq!(rules[0].explain_character_count(), None);
+        assert_eq!(rules[1].explain_character_count(), Some("expected 1 to 3 of \"b\" but found 0".to_owned()));
+        assert_eq!(rules[1].explain_character_position(), Some("neither position 1 (\"c\") nor position 3 (\"e\") is \"b\"".to_owned()));
+        assert_eq!(rules[2].explain_character_position(), Some("positions 2 and 9 are both \"c\"".to_owned()));
+        assert_eq!(
+            Rule::from_line("4-6 a: abc").unwrap().explain_character_position(),
+            Some("position 4 does not exist in the 3-character password".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_reports_violations() {
+        let report = ViolationReport::new(INPUT, RuleKind::Count);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.violations, vec![RuleViolation {
+            line: 2,
+            policy: "count 1-3 b".to_owned(),
+            password: "cdefg",
+            expected: "1-3".to_owned(),
+            observed: "0".to_owned(),
+            reason: "expected 1 to 3 of \"b\" but found 0".to_owned()
+        }]);
+        assert_eq!(report.characters, vec![
+            CharacterStats { character: "a", rules: 1, failures: 0 },
+            CharacterStats { character: "b", rules: 1, failures: 1 },
+            CharacterStats { character: "c", rules: 1, failures: 0 },
+        ]);
+        assert_eq!(report.summary_to_csv(), "character,rules,failures\na,1,0\nb,1,1\nc,1,0\n");
+        assert_eq!(summary_csv_part_two(INPUT), "\ncharacter,rules,failures\na,1,0\nb,1,1\nc,1,1\n");
+    }
+
+    #[test]
+    fn it_reports_malformed_rules() {
+        assert_eq!(Rule::from_line("1-3: abc").err(), Some("\"1-3: abc\" is not a valid rule".to_owned()));
+        let input = "1-3 a: abcde\nnonsense\n1-3 b: cdefg\n";
+        let report = ViolationReport::new(input, RuleKind::Count);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.malformed, vec![(2, "\"nonsense\" is not a valid rule".to_owned())]);
+        assert_eq!(report.violations[0].line, 3);
+        assert!(report.to_csv().ends_with("\n2,,,,,\"\"\"nonsense\"\" is not a valid rule\"\n"));
+        assert!(report.to_string().contains("  line 2: \"nonsense\" is not a valid rule\n"));
+    }
+
+    #[test]
+    fn it_exports_violations_as_csv() {
+        let report = ViolationReport::new(INPUT, RuleKind::Position);
+        assert_eq!(report.to_csv(), indoc! {r#"
+            line,policy,password,expected,observed,reason
+            2,position 1-3 b,cdefg,exactly one of 1 and 3,c e,"neither position 1 (""c"") nor position 3 (""e"") is ""b"""
+            3,position 2-9 c,ccccccccc,exactly one of 2 and 9,c c,"positions 2 and 9 are both ""c"""
+        "#});
+    }
+
+    #[test]
+    fn it_validates_policy_files() {
+        let policies = PolicySet::from_spec(POLICIES).unwrap();
+        assert!(policies.is_valid("CorrectHorse9"));
+        let failure = |policy: &str, reason: &str| Failure { policy: policy.to_owned(), reason: reason.to_owned() };
+        assert_eq!(policies.validate(PASSWORDS), vec![
+            Violation { line: 2, password: "hunter2", failures: vec![
+                failure("min-length 8", "expected at least 8 characters but found 7"),
+                failure("requires uppercase", "expected at least one uppercase character"),
+            ] },
+            Violation { line: 3, password: "MyPassword1", failures: vec![
+                failure("forbids password", "contains forbidden substring \"password\""),
+            ] },
+            Violation { line: 5, password: "Ünïcödé99Ab", failures: vec![
+                failure("matches ^[[:ascii:]]+$", "does not match /^[[:ascii:]]+$/"),
+            ] },
+        ]);
+    }
+
+    #[test]
+    fn it_parses_puzzle_policies() {
+        let policies = PolicySet::from_spec("count 1-3 a\nposition 1-3 a").unwrap();
+        assert!(policies.is_valid("abcde"));
+        assert!(!policies.is_valid("aaa"));
+        assert!(PolicySet::from_spec("count 1-3\nlength 5").is_err());
     }
 
-}
\ No newline at end of file
+}
@@ -1,45 +1,325 @@
+use std::ops::RangeInclusive;
+
 type Grid = Vec<Vec<bool>>;
 
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Wrap {
+    None,
+    Horizontal,
+    Vertical,
+    Both
+}
+
+impl Wrap {
+    fn horizontal(&self) -> bool {
+        matches!(self, Wrap::Horizontal | Wrap::Both)
+    }
+
+    fn vertical(&self) -> bool {
+        matches!(self, Wrap::Vertical | Wrap::Both)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Slope {
+    pub rise: usize,
+    pub run: isize
+}
+
+impl Slope {
+    pub fn new(rise: usize, run: isize) -> Option<Self> {
+        match gcd(rise, run.unsigned_abs()) {
+            0 => None,
+            divisor => Some(Slope { rise: rise / divisor, run: run / divisor as isize })
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Goal {
+    Fewest,
+    Most
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Cell {
+    pub row: usize,
+    pub column: usize,
+    pub tree: bool
+}
+
+pub struct TobogganMap {
+    grid: Grid,
+    width: usize
+}
+
+impl TobogganMap {
+
+    pub fn new(grid: Grid) -> Self {
+        let width = grid.first().map_or(0, Vec::len);
+        TobogganMap { grid, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn is_tree(&self, row: usize, column: usize) -> bool {
+        self.grid[row][column]
+    }
+
+    pub fn path(&self, slope: Slope, wrap: Wrap) -> Path<'_> {
+        let reduced = Slope::new(slope.rise, slope.run);
+        let done = self.width == 0 || self.height() == 0 || reduced.is_none();
+        Path { map: self, slope: reduced.unwrap_or(slope), wrap, row: 0, column: 0, done }
+    }
+
+    pub fn count_trees(&self, slope: Slope, wrap: Wrap) -> usize {
+        self.path(slope, wrap)
+            .filter(|cell| cell.tree)
+            .count()
+    }
+
+    pub fn find_slope(&self, rises: RangeInclusive<usize>, runs: RangeInclusive<isize>, goal: Goal) -> Option<(Slope, usize)> {
+        let mut seen = Vec::new();
+        let mut best: Option<(Slope, usize)> = None;
+        for rise in rises {
+            for run in runs.clone() {
+                let slope = match Slope::new(rise, run) {
+                    Some(slope) => slope,
+                    None => continue
+                };
+                if seen.contains(&slope) { continue }
+                seen.push(slope);
+                let count = self.count_trees(slope, Wrap::Horizontal);
+                let is_better = match (best, goal) {
+                    (None, _) => true,
+                    (Some((_, current)), Goal::Fewest) => count < current,
+                    (Some((_, current)), Goal::Most) => count > current
+                };
+                if is_better { best = Some((slope, count)) }
+            }
+        }
+        best
+    }
+
+    pub fn render_path(&self, slope: Slope) -> String {
+        if self.width == 0 || self.height() == 0 { return String::new() }
+        let slope = Slope::new(slope.rise, slope.run).unwrap_or(slope);
+        let width = self.width as isize;
+        let steps = match slope.rise {
+            0 => 0,
+            rise => (self.height() - 1) / rise
+        };
+        let columns = (0..=steps as isize).map(|step| step * slope.run);
+        let left = columns.clone().min().unwrap_or(0).min(0).div_euclid(width);
+        let right = columns.max().unwrap_or(0).div_euclid(width) + 1;
+
+        let mut output = String::new();
+        for (row, line) in self.grid.iter().enumerate() {
+            let on_path = match slope.rise {
+                0 => None,
+                rise if row % rise == 0 && row > 0 => Some((row / rise) as isize * slope.run),
+                _ => None
+            };
+            for column in left * width..right * width {
+                let tree = line[column.rem_euclid(width) as usize];
+                let c = match (on_path == Some(column), tree) {
+                    (true, true) => 'X',
+                    (true, false) => 'O',
+                    (false, true) => '#',
+                    (false, false) => '.'
+                };
+                output.push(c);
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+}
+
+pub struct Path<'a> {
+    map: &'a TobogganMap,
+    slope: Slope,
+    wrap: Wrap,
+    row: usize,
+    column: usize,
+    done: bool
+}
+
+impl <'a> Iterator for Path<'a> {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        if self.done { return None }
+        let height = self.map.height() as isize;
+        let width = self.map.width() as isize;
+        let mut row = self.row as isize + self.slope.rise as isize;
+        let mut column = self.column as isize + self.slope.run;
+
+        if self.wrap.vertical() { row = row.rem_euclid(height) }
+        if self.wrap.horizontal() { column = column.rem_euclid(width) }
+        if row >= height || column < 0 || column >= width || (row, column) == (0, 0) {
+            self.done = true;
+            return None;
+        }
+
+        self.row = row as usize;
+        self.column = column as usize;
+        Some(Cell { row: self.row, column: self.column, tree: self.map.is_tree(self.row, self.column) })
+    }
+}
+
+pub struct PackedGrid {
+    words: Vec<u64>,
+    stride: usize,
+    width: usize,
+    height: usize
+}
+
+impl PackedGrid {
+
+    pub fn from_input(input: &str) -> Self {
+        let width = input.lines().next().map_or(0, str::len);
+        let stride = width.div_ceil(64);
+        let mut words = Vec::new();
+        let mut height = 0;
+        for line in input.lines() {
+            let start = words.len();
+            words.resize(start + stride, 0u64);
+            line.bytes()
+                .enumerate()
+                .filter(|&(_, byte)| byte == b'#')
+                .for_each(|(column, _)| words[start + column / 64] |= 1 << (column % 64));
+            height += 1;
+        }
+        PackedGrid { words, stride, width, height }
+    }
+
+    pub fn is_tree(&self, row: usize, column: usize) -> bool {
+        (self.words[row * self.stride + column / 64] >> (column % 64)) & 1 == 1
+    }
+
+    pub fn count_trees(&self, slope: Slope) -> usize {
+        self.count_trees_many(&[slope])[0]
+    }
+
+    fn count_trees_in_first_row(&self, run: usize) -> usize {
+        let step = gcd(run, self.width);
+        (1..self.width / step)
+            .filter(|k| self.is_tree(0, k * step))
+            .count()
+    }
+
+    pub fn count_trees_many(&self, slopes: &[Slope]) -> Vec<usize> {
+        if self.width == 0 || self.height == 0 { return vec![0; slopes.len()] }
+        let slopes = slopes.iter()
+            .map(|slope| Slope::new(slope.rise, slope.run))
+            .map(|slope| slope.map(|slope| (slope.rise, slope.run.rem_euclid(self.width as isize) as usize)))
+            .collect::<Vec<_>>();
+        let mut columns = vec![0; slopes.len()];
+        let mut counts = slopes.iter()
+            .map(|slope| match slope {
+                Some((0, run)) => self.count_trees_in_first_row(*run),
+                _ => 0
+            })
+            .collect::<Vec<_>>();
+        for row in 1..self.height {
+            let words = &self.words[row * self.stride..(row + 1) * self.stride];
+            for (index, slope) in slopes.iter().enumerate() {
+                let (rise, run) = match slope {
+                    Some((rise, run)) if *rise > 0 => (*rise, *run),
+                    _ => continue
+                };
+                if row % rise != 0 { continue }
+                let column = (columns[index] + run) % self.width;
+                columns[index] = column;
+                counts[index] += ((words[column / 64] >> (column % 64)) & 1) as usize;
+            }
+        }
+        counts
+    }
+
+}
+
 #[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> Vec<Vec<bool>> {
-    input.lines()
+pub fn input_generator(input: &str) -> TobogganMap {
+    let grid = input.lines()
         .map(|line| line.chars().map(|c| c == '#').collect())
-        .collect()
+        .collect();
+    TobogganMap::new(grid)
 }
 
-fn count_trees(input: &Grid, (rise, run): (usize, usize)) -> usize {
-    let mut height = 0;
-    let mut position = 0;
-    let mut count = 0;
-    let width = input[0].len();
-    loop {
-        position = (position + run) % width;
-        height = height + rise;
-        if height >= input.len() { break }
-        if input[height][position] { count += 1 }
-    }
-    count
+fn count_trees(input: &TobogganMap, (rise, run): (usize, usize)) -> usize {
+    input.count_trees(Slope::new(rise, run as isize).unwrap(), Wrap::Horizontal)
 }
 
 #[aoc(day3, part1)]
-pub fn solve_part_one(input: &Grid) -> usize {
+pub fn solve_part_one(input: &TobogganMap) -> usize {
     count_trees(input, (1,3))
 }
 
+const SLOPES: [(usize, usize); 5] = [
+    (1, 1),
+    (1, 3),
+    (1, 5),
+    (1, 7),
+    (2, 1),
+];
+
 #[aoc(day3, part2)]
-pub fn solve_part_two(input: &Grid) -> usize {
-    let slopes = [
-        (1, 1),
-        (1, 3),
-        (1, 5),
-        (1, 7),
-        (2, 1),
-    ];
-    slopes.iter()
+pub fn solve_part_two(input: &TobogganMap) -> usize {
+    SLOPES.iter()
         .map(|&slope| count_trees(input, slope))
         .product()
 }
 
+#[aoc_generator(day3, part1, Packed)]
+#[aoc_generator(day3, part2, Packed)]
+pub fn packed_generator(input: &str) -> PackedGrid {
+    PackedGrid::from_input(input)
+}
+
+#[aoc(day3, part1, Packed)]
+pub fn solve_part_one_packed(input: &PackedGrid) -> usize {
+    input.count_trees(Slope::new(1, 3).unwrap())
+}
+
+#[aoc(day3, part2, Packed)]
+pub fn solve_part_two_packed(input: &PackedGrid) -> usize {
+    let slopes = SLOPES.iter()
+        .map(|&(rise, run)| Slope::new(rise, run as isize).unwrap())
+        .collect::<Vec<_>>();
+    input.count_trees_many(&slopes)
+        .iter()
+        .product()
+}
+
+#[aoc(day3, part1, Render)]
+pub fn render_part_one(input: &TobogganMap) -> String {
+    format!("\n{}", input.render_path(Slope::new(1, 3).unwrap()))
+}
+
+#[aoc(day3, part2, Fewest)]
+pub fn solve_fewest_trees(input: &TobogganMap) -> String {
+    match input.find_slope(1..=2, 1..=7, Goal::Fewest) {
+        Some((slope, count)) => format!("right {}, down {}: {} trees", slope.run, slope.rise, count),
+        None => "no slope found".to_owned()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -71,4 +351,99 @@ mod test {
         assert_eq!(solve_part_two(&input), 336);
     }
 
+    #[test]
+    fn it_walks_rational_slopes() {
+        let input = input_generator(INPUT);
+        let coarse = input.path(Slope::new(2, 4).unwrap(), Wrap::Horizontal).collect::<Vec<_>>();
+        let fine = input.path(Slope::new(1, 2).unwrap(), Wrap::Horizontal).collect::<Vec<_>>();
+        assert_eq!(coarse, fine);
+        assert_eq!(Slope::new(0, 0), None);
+        assert_eq!(input.path(Slope { rise: 0, run: 0 }, Wrap::Both).count(), 0);
+        assert_eq!(fine.len(), 10);
+        assert_eq!(fine[0], Cell { row: 1, column: 2, tree: false });
+    }
+
+    #[test]
+    fn it_walks_negative_runs() {
+        let input = input_generator(INPUT);
+        let path = input.path(Slope::new(1, -3).unwrap(), Wrap::Horizontal).collect::<Vec<_>>();
+        assert_eq!(path[0], Cell { row: 1, column: 8, tree: true });
+        assert_eq!(path.len(), 10);
+        assert_eq!(input.path(Slope::new(1, -3).unwrap(), Wrap::None).count(), 0);
+    }
+
+    #[test]
+    fn it_respects_wrap_modes() {
+        let input = input_generator(INPUT);
+        let bounded = input.path(Slope::new(1, 3).unwrap(), Wrap::None).collect::<Vec<_>>();
+        assert_eq!(bounded.last(), Some(&Cell { row: 3, column: 9, tree: false }));
+        assert_eq!(bounded.len(), 3);
+        let vertical = input.path(Slope::new(3, 1).unwrap(), Wrap::Vertical).collect::<Vec<_>>();
+        assert_eq!(vertical.len(), 10);
+        assert_eq!(vertical[3], Cell { row: 1, column: 4, tree: true });
+        let both = input.path(Slope::new(1, 1).unwrap(), Wrap::Both).collect::<Vec<_>>();
+        assert_eq!(both.len(), 10);
+        assert_eq!(both.last(), Some(&Cell { row: 10, column: 10, tree: true }));
+    }
+
+    #[test]
+    fn it_solves_packed() {
+        let input = packed_generator(INPUT);
+        assert_eq!(solve_part_one_packed(&input), 7);
+        assert_eq!(solve_part_two_packed(&input), 336);
+    }
+
+    #[test]
+    fn it_matches_the_map_when_packed() {
+        let wide = INPUT.lines().map(|line| line.repeat(7) + "\n").collect::<String>();
+        let map = input_generator(&wide);
+        let packed = packed_generator(&wide);
+        assert!(packed.is_tree(1, 70) && !packed.is_tree(1, 71));
+        let slopes = [Slope::new(1, 3).unwrap(), Slope::new(2, -5).unwrap(), Slope::new(1, 70).unwrap(), Slope::new(3, 9).unwrap()];
+        let expected = slopes.iter()
+            .map(|&slope| map.count_trees(slope, Wrap::Horizontal))
+            .collect::<Vec<_>>();
+        assert_eq!(packed.count_trees_many(&slopes), expected);
+    }
+
+    #[test]
+    fn it_handles_degenerate_packed_slopes() {
+        let map = input_generator(INPUT);
+        let packed = packed_generator(INPUT);
+        let slopes = [Slope { rise: 0, run: 0 }, Slope { rise: 0, run: 2 }, Slope { rise: 0, run: -11 }, Slope::new(1, 3).unwrap()];
+        let expected = slopes.iter()
+            .map(|&slope| map.count_trees(slope, Wrap::Horizontal))
+            .collect::<Vec<_>>();
+        assert_eq!(packed.count_trees_many(&slopes), expected);
+        assert_eq!(expected[1], 2);
+        assert_eq!(packed_generator("").count_trees_many(&slopes), vec![0; 4]);
+    }
+
+    #[test]
+    fn it_finds_optimal_slopes() {
+        let input = input_generator(INPUT);
+        assert_eq!(input.find_slope(1..=2, 1..=7, Goal::Most), Some((Slope::new(1, 3).unwrap(), 7)));
+        assert_eq!(input.find_slope(1..=2, 1..=7, Goal::Fewest), Some((Slope::new(2, 5).unwrap(), 0)));
+        assert_eq!(input.find_slope(0..=0, 0..=0, Goal::Fewest), None);
+    }
+
+    #[test]
+    fn it_renders_paths() {
+        let input = input_generator(INPUT);
+        assert_eq!(input_generator("").render_path(Slope::new(1, 3).unwrap()), "");
+        assert_eq!(input.render_path(Slope::new(1, 3).unwrap()), indoc! {"
+            ..##.........##.........##.......
+            #..O#...#..#...#...#..#...#...#..
+            .#....X..#..#....#..#..#....#..#.
+            ..#.#...#O#..#.#...#.#..#.#...#.#
+            .#...##..#..X...##..#..#...##..#.
+            ..#.##.......#.X#.......#.##.....
+            .#.#.#....#.#.#.#.O..#.#.#.#....#
+            .#........#.#........X.#........#
+            #.##...#...#.##...#...#.X#...#...
+            #...##....##...##....##...#X....#
+            .#..#...#.#.#..#...#.#.#..#...X.#
+        "});
+    }
+
 }
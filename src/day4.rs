@@ -1,106 +1,586 @@
-use std::{str::FromStr, collections::HashMap};
+use std::{str::FromStr, collections::HashMap, convert::TryFrom, fmt, fs, io::{self, BufRead}, path::Path};
+use crate::groups::{groups, BlankLineGroups};
+use serde::{Deserialize, Serialize};
 use nom::{IResult, character::complete::{alpha1, digit1}, combinator::map_res, bytes::complete::tag, sequence::tuple};
 
-type Passport = HashMap<String, String>;
+type Record = Vec<(String, String)>;
 
-#[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Vec<Passport> {
-    let mut output = Vec::new();
-    output.push(HashMap::new());
-    let lines = input.lines();
-    
-    for line in lines {
-        if line.is_empty() {
-            output.push(HashMap::new());
-            continue;
-        }
-        let current = output.last_mut().unwrap();
-        for part in line.split_whitespace() {
-            let field_parts: Vec<&str> = part.split(':').collect();
-            current.insert(field_parts[0].to_owned(), field_parts[1].to_owned());
-        }
-    }
+fn parse_record(group: &[String]) -> Record {
+    group.iter()
+        .flat_map(|line| line.split_whitespace())
+        .map(|part| {
+            let mut field_parts = part.splitn(2, ':');
+            let key = field_parts.next().unwrap_or_default();
+            let value = field_parts.next().unwrap_or_default();
+            (key.to_owned(), value.to_owned())
+        })
+        .collect()
+}
 
-    if output.last().unwrap().is_empty() { output.pop(); }
-    output
+pub fn read_records<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<Record>> {
+    BlankLineGroups::new(reader)
+        .map(|group| group.map(|group| parse_record(&group)))
 }
 
-const REQUIRED_FIELDS: &'static [&str] = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-const EYE_COLORS: &'static [&str] = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+#[aoc_generator(day4)]
+pub fn input_generator(input: &str) -> Vec<Record> {
+    groups(input)
+        .map(|group| parse_record(&group))
+        .collect()
+}
+
+const EYE_COLORS: &[&str] = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+const BIRTH_YEARS: (usize, usize) = (1920, 2002);
+const ISSUE_YEARS: (usize, usize) = (2010, 2020);
+const EXPIRATION_YEARS: (usize, usize) = (2020, 2030);
+const HEIGHTS: [(&str, usize, usize); 2] = [("cm", 150, 193), ("in", 59, 76)];
+const PASSPORT_ID_DIGITS: usize = 9;
 
-fn year(input: &str, min: usize, max: usize) -> bool {
+fn year(input: &str, min: usize, max: usize) -> Result<usize, String> {
     let result: IResult<&str, usize> = map_res(digit1, FromStr::from_str)(input);
     match result {
-        Ok((_, year)) => year >= min && year <= max,
-        _ => false
+        Ok((_, year)) if year >= min && year <= max => Ok(year),
+        Ok(_) => Err(format!("{} out of range {}..={}", input, min, max)),
+        _ => Err(format!("{} is not a year", input))
     }
 }
 
-fn height(input: &str) -> bool {
+fn height<'a>(input: &str, units: &'a [Unit]) -> Result<(usize, &'a Unit), String> {
     let result: IResult<&str, (usize, &str)> = tuple(( map_res(digit1, FromStr::from_str), alpha1 ))(input);
+    let names = units.iter().map(|u| u.unit.as_str()).collect::<Vec<_>>().join(", ");
     match result {
-        Ok((_, (num, "cm"))) => num >= 150 && num <= 193,
-        Ok((_, (num, "in"))) => num >= 59 && num <= 76,
-        _ => false
+        Ok((_, (num, unit))) => match units.iter().find(|u| u.unit == unit) {
+            Some(u) if num >= u.min && num <= u.max => Ok((num, u)),
+            Some(u) => Err(format!("{} out of range {}..={}", input, u.min, u.max)),
+            None => Err(format!("{} has unknown unit {}, expected one of {}", input, unit, names))
+        },
+        _ => Err(format!("{} is not a number followed by one of {}", input, names))
     }
 }
 
-fn hex(input: &str) -> bool {
+fn hex(input: &str) -> Result<[u8; 3], String> {
     let result: IResult<&str, &str> = tag("#")(input);
     match result {
-        Ok((value, _)) => {
-            if value.len() != 6 { return false }
-            value.chars().all(|c| c.is_digit(16))
+        Ok((value, _)) if value.len() == 6 && value.chars().all(|c| c.is_ascii_hexdigit()) => {
+            let channel = |i: usize| u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).unwrap();
+            Ok([channel(0), channel(1), channel(2)])
         },
-        Err(_) => false
+        _ => Err(format!("{} is not a # followed by 6 hex digits", input))
+    }
+}
+
+fn one_of<T: AsRef<str>>(input: &str, values: &[T]) -> Result<(), String> {
+    match values.iter().any(|v| v.as_ref() == input) {
+        true => Ok(()),
+        false => {
+            let values = values.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+            Err(format!("{} is not one of {}", input, values.join(", ")))
+        }
+    }
+}
+
+fn digits(input: &str, length: usize) -> Result<(), String> {
+    match input.len() == length && input.chars().all(|c| c.is_ascii_digit()) {
+        true => Ok(()),
+        false => Err(format!("{} is not {} digits", input, length))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unit {
+    pub unit: String,
+    pub min: usize,
+    pub max: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Validator {
+    #[default]
+    Any,
+    Year { min: usize, max: usize },
+    Measurement { units: Vec<Unit> },
+    HexColor,
+    OneOf { values: Vec<String> },
+    Digits { length: usize }
+}
+
+impl Validator {
+    pub fn check(&self, input: &str) -> Result<(), String> {
+        match self {
+            Validator::Any => Ok(()),
+            Validator::Year { min, max } => year(input, *min, *max).map(|_| ()),
+            Validator::Measurement { units } => height(input, units).map(|_| ()),
+            Validator::HexColor => hex(input).map(|_| ()),
+            Validator::OneOf { values } => one_of(input, values),
+            Validator::Digits { length } => digits(input, *length)
+        }
+    }
+
+    pub fn is_valid(&self, input: &str) -> bool {
+        self.check(input).is_ok()
     }
 }
 
-fn eye_color(input: &str) -> bool {
-    EYE_COLORS.iter().any(|&v| v == input)
+fn last_occurrences(record: &[(String, String)]) -> impl Iterator<Item = &(String, String)> {
+    record.iter()
+        .enumerate()
+        .filter(move |(index, (key, _))| !record[index + 1..].iter().any(|(other, _)| other == key))
+        .map(|(_, field)| field)
 }
 
-fn pid(input: &str) -> bool {
-    if input.len() != 9 { return false }
-    input.chars().all(|c| c.is_digit(10))
+fn height_units() -> Vec<Unit> {
+    HEIGHTS.iter()
+        .map(|&(unit, min, max)| Unit { unit: unit.to_owned(), min, max })
+        .collect()
 }
 
-pub fn contains_all_fields(passport: &&Passport) -> bool {
-    REQUIRED_FIELDS.iter().all(|f| passport.contains_key(*f))
+fn default_required() -> bool { true }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    #[serde(default)]
+    pub validator: Validator
 }
 
-pub fn validate_fields(passport: &&Passport) -> bool {
-    passport.iter()
-        .all(|(key, value)| {
-            match key.as_str() {
-                "byr" => year(value, 1920, 2002),
-                "iyr" => year(value, 2010, 2020),
-                "eyr" => year(value, 2020, 2030),
-                "hgt" => height(value),
-                "hcl" => hex(value),
-                "ecl" => eye_color(value),
-                "pid" => pid(value),
-                "cid" => true,
-                _ => false
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub fields: Vec<FieldSpec>,
+    #[serde(default)]
+    pub allow_unknown: bool
+}
+
+impl Schema {
+
+    pub fn puzzle() -> Self {
+        let field = |name: &str, validator| FieldSpec { name: name.to_owned(), required: true, validator };
+        let years = |(min, max)| Validator::Year { min, max };
+        Schema {
+            fields: vec![
+                field("byr", years(BIRTH_YEARS)),
+                field("iyr", years(ISSUE_YEARS)),
+                field("eyr", years(EXPIRATION_YEARS)),
+                field("hgt", Validator::Measurement { units: height_units() }),
+                field("hcl", Validator::HexColor),
+                field("ecl", Validator::OneOf { values: EYE_COLORS.iter().map(|c| c.to_string()).collect() }),
+                field("pid", Validator::Digits { length: PASSPORT_ID_DIGITS }),
+                FieldSpec { name: "cid".to_owned(), required: false, validator: Validator::Any },
+            ],
+            allow_unknown: false
+        }
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, String> {
+        serde_json::from_str(input).map_err(|e| e.to_string())
+    }
+
+    pub fn from_toml(input: &str) -> Result<Self, String> {
+        toml::from_str(input).map_err(|e| e.to_string())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Schema::from_json(&contents),
+            Some("toml") => Schema::from_toml(&contents),
+            _ => Err(format!("unsupported schema format: {}", path.display()))
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldSpec> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    pub fn contains_all_fields(&self, passport: &[(String, String)]) -> bool {
+        self.fields.iter()
+            .filter(|f| f.required)
+            .all(|f| passport.iter().any(|(key, _)| *key == f.name))
+    }
+
+    pub fn validate_fields(&self, passport: &[(String, String)]) -> bool {
+        last_occurrences(passport)
+            .all(|(key, value)| match self.field(key) {
+                Some(field) => field.validator.is_valid(value),
+                None => self.allow_unknown
+            })
+    }
+
+    pub fn diagnose(&self, record: &[(String, String)]) -> Diagnostics {
+        let mut diagnostics = Diagnostics::default();
+        let mut seen: Vec<&str> = Vec::new();
+        for (key, _) in record.iter() {
+            if seen.contains(&key.as_str()) {
+                if !diagnostics.duplicates.contains(key) { diagnostics.duplicates.push(key.clone()) }
+            } else {
+                seen.push(key);
             }
-        })
+        }
+        for (key, value) in last_occurrences(record) {
+            match self.field(key) {
+                Some(field) => if let Err(reason) = field.validator.check(value) {
+                    diagnostics.invalid.push(FieldError { field: key.clone(), reason });
+                },
+                None => if !self.allow_unknown { diagnostics.unknown.push(key.clone()) }
+            }
+        }
+        diagnostics.missing = self.fields.iter()
+            .filter(|f| f.required && !seen.contains(&f.name.as_str()))
+            .map(|f| f.name.clone())
+            .collect();
+        diagnostics
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.field, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diagnostics {
+    pub missing: Vec<String>,
+    pub invalid: Vec<FieldError>,
+    pub unknown: Vec<String>,
+    pub duplicates: Vec<String>
+}
+
+impl Diagnostics {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty() && self.unknown.is_empty()
+    }
+
+    pub fn failures(&self) -> Vec<String> {
+        self.missing.iter().map(|f| format!("missing {}", f))
+            .chain(self.invalid.iter().map(|e| format!("invalid {}", e.field)))
+            .chain(self.unknown.iter().map(|f| format!("unknown {}", f)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() { parts.push(format!("missing {}", self.missing.join(", "))) }
+        parts.extend(self.invalid.iter().map(ToString::to_string));
+        if !self.unknown.is_empty() { parts.push(format!("unknown {}", self.unknown.join(", "))) }
+        if !self.duplicates.is_empty() { parts.push(format!("duplicate {} (last value used)", self.duplicates.join(", "))) }
+        match parts.is_empty() {
+            true => write!(f, "valid"),
+            false => write!(f, "{}", parts.join("; "))
+        }
+    }
+}
+
+pub struct BatchReport {
+    pub passports: Vec<Diagnostics>
+}
+
+impl BatchReport {
+
+    pub fn new(records: &[Record], schema: &Schema) -> Self {
+        BatchReport { passports: records.iter().map(|record| schema.diagnose(record)).collect() }
+    }
+
+    pub fn valid(&self) -> usize {
+        self.passports.iter().filter(|d| d.is_valid()).count()
+    }
+
+    pub fn most_common_failures(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        self.passports.iter()
+            .flat_map(Diagnostics::failures)
+            .for_each(|failure| *counts.entry(failure).or_insert(0) += 1);
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+        counts
+    }
+
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln!(f, "{} of {} passports are valid", self.valid(), self.passports.len())?;
+        for (index, diagnostics) in self.passports.iter().enumerate().filter(|(_, d)| !d.is_valid()) {
+            writeln!(f, "  passport {}: {}", index + 1, diagnostics)?;
+        }
+        writeln!(f, "most common failures:")?;
+        for (failure, count) in self.most_common_failures().iter() {
+            writeln!(f, "  {:>5} {}", count, failure)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! year_type {
+    ($name:ident, $range:expr) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct $name(pub u16);
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(input: &str) -> Result<Self, Self::Err> {
+                let (min, max) = $range;
+                year(input, min, max).map(|year| $name(year as u16))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+year_type!(BirthYear, BIRTH_YEARS);
+year_type!(IssueYear, ISSUE_YEARS);
+year_type!(ExpirationYear, EXPIRATION_YEARS);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthUnit {
+    Centimeters,
+    Inches
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Height {
+    pub value: u16,
+    pub unit: LengthUnit
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let units = height_units();
+        let (value, unit) = height(input, &units)?;
+        let unit = match unit.unit.as_str() {
+            "cm" => LengthUnit::Centimeters,
+            _ => LengthUnit::Inches
+        };
+        Ok(Height { value: value as u16, unit })
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.unit {
+            LengthUnit::Centimeters => write!(f, "{}cm", self.value),
+            LengthUnit::Inches => write!(f, "{}in", self.value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HairColor(pub [u8; 3]);
+
+impl FromStr for HairColor {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        hex(input).map(HairColor)
+    }
+}
+
+impl fmt::Display for HairColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other
+}
+
+const EYE_COLOR_VALUES: [EyeColor; 7] = [
+    EyeColor::Amber, EyeColor::Blue, EyeColor::Brown, EyeColor::Gray, EyeColor::Green, EyeColor::Hazel, EyeColor::Other
+];
+
+impl FromStr for EyeColor {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        one_of(input, EYE_COLORS)?;
+        let index = EYE_COLORS.iter().position(|&c| c == input).unwrap();
+        Ok(EYE_COLOR_VALUES[index])
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index = EYE_COLOR_VALUES.iter().position(|c| c == self).unwrap();
+        write!(f, "{}", EYE_COLORS[index])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassportId(pub u32);
+
+impl FromStr for PassportId {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        digits(input, PASSPORT_ID_DIGITS)?;
+        Ok(PassportId(input.parse().unwrap()))
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:09}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountryId(pub String);
+
+impl FromStr for CountryId {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(CountryId(input.to_owned()))
+    }
+}
+
+impl fmt::Display for CountryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passport {
+    pub birth_year: BirthYear,
+    pub issue_year: IssueYear,
+    pub expiration_year: ExpirationYear,
+    pub height: Height,
+    pub hair_color: HairColor,
+    pub eye_color: EyeColor,
+    pub passport_id: PassportId,
+    pub country_id: Option<CountryId>
+}
+
+const PASSPORT_FIELDS: &[&str] = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+fn typed_field<T: FromStr<Err = String>>(record: &[(String, String)], name: &str, diagnostics: &mut Diagnostics) -> Option<T> {
+    let (_, value) = record.iter().rev().find(|(key, _)| key == name)?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(reason) => {
+            diagnostics.invalid.push(FieldError { field: name.to_owned(), reason });
+            None
+        }
+    }
+}
+
+impl TryFrom<&[(String, String)]> for Passport {
+    type Error = Diagnostics;
+
+    fn try_from(record: &[(String, String)]) -> Result<Self, Self::Error> {
+        let mut diagnostics = Diagnostics::default();
+        for (key, _) in record.iter() {
+            if !PASSPORT_FIELDS.contains(&key.as_str()) {
+                diagnostics.unknown.push(key.clone());
+            }
+        }
+        diagnostics.missing = PASSPORT_FIELDS.iter()
+            .filter(|&&name| name != "cid" && !record.iter().any(|(key, _)| key == name))
+            .map(|name| name.to_string())
+            .collect();
+
+        let passport = (
+            typed_field(record, "byr", &mut diagnostics),
+            typed_field(record, "iyr", &mut diagnostics),
+            typed_field(record, "eyr", &mut diagnostics),
+            typed_field(record, "hgt", &mut diagnostics),
+            typed_field(record, "hcl", &mut diagnostics),
+            typed_field(record, "ecl", &mut diagnostics),
+            typed_field(record, "pid", &mut diagnostics),
+        );
+        let country_id = typed_field(record, "cid", &mut diagnostics);
+        match passport {
+            (Some(birth_year), Some(issue_year), Some(expiration_year), Some(height), Some(hair_color), Some(eye_color), Some(passport_id))
+                if diagnostics.is_valid() =>
+                Ok(Passport { birth_year, issue_year, expiration_year, height, hair_color, eye_color, passport_id, country_id }),
+            _ => Err(diagnostics)
+        }
+    }
+}
+
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.birth_year, self.issue_year, self.expiration_year, self.height, self.hair_color, self.eye_color, self.passport_id
+        )?;
+        match &self.country_id {
+            Some(country_id) => write!(f, " cid:{}", country_id),
+            None => Ok(())
+        }
+    }
+}
+
+pub fn serialize_batch(passports: &[Passport]) -> String {
+    passports.iter()
+        .map(|passport| format!("{}\n", passport))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn contains_all_fields(passport: &&Record) -> bool {
+    Schema::puzzle().contains_all_fields(passport)
+}
+
+pub fn validate_fields(passport: &&Record) -> bool {
+    Schema::puzzle().validate_fields(passport)
+}
+
+pub fn count_valid(input: &[Record], schema: &Schema) -> usize {
+    input.iter()
+        .filter(|passport| schema.contains_all_fields(passport))
+        .filter(|passport| schema.validate_fields(passport))
+        .count()
 }
 
 #[aoc(day4, part1)]
-pub fn solve_part_one(input: &[Passport]) -> usize {
+pub fn solve_part_one(input: &[Record]) -> usize {
     input.iter()
         .filter(contains_all_fields)
         .count()
 }
 
 #[aoc(day4, part2)]
-pub fn solve_part_two(input: &[Passport]) -> usize {
+pub fn solve_part_two(input: &[Record]) -> usize {
     input.iter()
-        .filter(contains_all_fields)
-        .filter(validate_fields)
+        .filter_map(|record| Passport::try_from(record.as_slice()).ok())
         .count()
 }
 
+#[aoc(day4, part2, Report)]
+pub fn report_part_two(input: &[Record]) -> String {
+    BatchReport::new(input, &Schema::puzzle()).to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -167,4 +647,109 @@ mod test {
         assert_eq!(solve_part_two(&invalid_input), 0);
     }
 
+    const SCHEMA_JSON: &str = r#"{
+        "fields": [
+            { "name": "byr", "validator": { "kind": "year", "min": 1900, "max": 2000 } },
+            { "name": "hgt", "validator": { "kind": "measurement", "units": [{ "unit": "cm", "min": 100, "max": 250 }] } },
+            { "name": "ecl", "required": false, "validator": { "kind": "one-of", "values": ["amb", "blu", "grn"] } }
+        ],
+        "allow_unknown": true
+    }"#;
+
+    const SCHEMA_TOML: &str = indoc! {r#"
+        allow_unknown = true
+
+        [[fields]]
+        name = "byr"
+        validator = { kind = "year", min = 1900, max = 2000 }
+
+        [[fields]]
+        name = "hgt"
+        validator = { kind = "measurement", units = [{ unit = "cm", min = 100, max = 250 }] }
+
+        [[fields]]
+        name = "ecl"
+        required = false
+        validator = { kind = "one-of", values = ["amb", "blu", "grn"] }
+    "#};
+
+    #[test]
+    fn it_diagnoses_passports() {
+        let report = BatchReport::new(&input_generator(INVALID), &Schema::puzzle());
+        assert_eq!(report.valid(), 0);
+        assert_eq!(
+            report.passports[0].to_string(),
+            "eyr 1972 out of range 2020..=2030; hgt 170 is not a number followed by one of cm, in; pid 186cm is not 9 digits"
+        );
+        assert_eq!(report.passports[3].invalid[0], FieldError { field: "hgt".to_owned(), reason: "59cm out of range 150..=193".to_owned() });
+        assert_eq!(report.most_common_failures()[0], ("invalid eyr".to_owned(), 3));
+
+        let diagnostics = Schema::puzzle().diagnose(&input_generator("hgt:190in byr:1980 byr:1981 foo:bar")[0]);
+        assert_eq!(diagnostics.missing, vec!["iyr", "eyr", "hcl", "ecl", "pid"]);
+        assert_eq!(diagnostics.invalid[0].to_string(), "hgt 190in out of range 59..=76");
+        assert_eq!(diagnostics.unknown, vec!["foo"]);
+        assert_eq!(diagnostics.duplicates, vec!["byr"]);
+    }
+
+    #[test]
+    fn it_converts_typed_passports() {
+        let records = input_generator(VALID);
+        let passports = records.iter()
+            .map(|record| Passport::try_from(record.as_slice()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(passports[0].height, Height { value: 74, unit: LengthUnit::Inches });
+        assert_eq!(passports[0].hair_color, HairColor([0x62, 0x3a, 0x2f]));
+        assert_eq!(passports[1].eye_color, EyeColor::Blue);
+        assert_eq!(passports[3].passport_id, PassportId(93154719));
+        assert_eq!(passports[1].country_id, Some(CountryId("129".to_owned())));
+
+        let serialized = serialize_batch(&passports);
+        assert!(serialized.starts_with("byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704\n\n"));
+        let reparsed = input_generator(&serialized).iter()
+            .map(|record| Passport::try_from(record.as_slice()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(reparsed, passports);
+
+        let errors = Passport::try_from(input_generator(INVALID)[3].as_slice()).unwrap_err();
+        assert_eq!(errors.invalid.len(), 7);
+        assert_eq!(errors.invalid[0].to_string(), "byr 2007 out of range 1920..=2002");
+
+        let repeated = input_generator("byr:1800 byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327");
+        assert_eq!(solve_part_two(&repeated), 1);
+        assert_eq!(Passport::try_from(repeated[0].as_slice()).unwrap().birth_year, BirthYear(1937));
+        let diagnostics = Schema::puzzle().diagnose(&repeated[0]);
+        assert_eq!(diagnostics.duplicates, vec!["byr"]);
+        assert!(diagnostics.is_valid());
+        assert_eq!(count_valid(&repeated, &Schema::puzzle()), 1);
+        assert_eq!(BatchReport::new(&repeated, &Schema::puzzle()).valid(), 1);
+    }
+
+    #[test]
+    fn it_streams_records() {
+        let records = read_records(INPUT.replace('\n', "\r\n").as_bytes())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records, input_generator(INPUT));
+        assert_eq!(solve_part_one(&records), 2);
+    }
+
+    #[test]
+    fn it_loads_alternative_schemas() {
+        let json = Schema::from_json(SCHEMA_JSON).unwrap();
+        let toml = Schema::from_toml(SCHEMA_TOML).unwrap();
+        assert_eq!(json, toml);
+        assert_eq!(json.field("pid"), None);
+
+        let input = input_generator(VALID);
+        assert_eq!(count_valid(&input, &json), 2);
+        assert!(Schema::from_json(r#"{ "fields": [{ "name": "byr", "validator": { "kind": "date" } }] }"#).is_err());
+    }
+
+    #[test]
+    fn it_round_trips_the_puzzle_schema() {
+        let schema = Schema::puzzle();
+        let json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(Schema::from_json(&json), Ok(schema));
+    }
+
 }
@@ -1,27 +1,94 @@
+use std::{error::Error, fmt, str::FromStr};
+
+#[derive(Debug, PartialEq)]
+pub enum SeatError {
+    InvalidLength { expected: usize, found: usize },
+    InvalidCharacter { position: usize, character: char },
+    OutOfRange(usize)
+}
+
+impl fmt::Display for SeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeatError::InvalidLength { expected, found } =>
+                write!(f, "expected {} characters but found {}", expected, found),
+            SeatError::InvalidCharacter { position, character } =>
+                write!(f, "invalid character {:?} at position {}", character, position),
+            SeatError::OutOfRange(id) =>
+                write!(f, "seat id {} does not fit the plane", id)
+        }
+    }
+}
+
+impl Error for SeatError {}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct SeatCode<const ROW_BITS: usize, const COLUMN_BITS: usize> {
+    pub row: usize,
+    pub column: usize
+}
+
+pub type BoardingPass = SeatCode<7, 3>;
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> SeatCode<ROW_BITS, COLUMN_BITS> {
+
+    pub const ROWS: usize = 1 << ROW_BITS;
+    pub const COLUMNS: usize = 1 << COLUMN_BITS;
+    pub const SEATS: usize = 1 << (ROW_BITS + COLUMN_BITS);
+
+    pub fn new(row: usize, column: usize) -> Result<Self, SeatError> {
+        if row >= Self::ROWS || column >= Self::COLUMNS {
+            return Err(SeatError::OutOfRange(row * Self::COLUMNS + column))
+        }
+        Ok(SeatCode { row, column })
+    }
+
+    pub fn from_id(id: usize) -> Result<Self, SeatError> {
+        if id >= Self::SEATS { return Err(SeatError::OutOfRange(id)) }
+        Ok(SeatCode { row: id >> COLUMN_BITS, column: id & (Self::COLUMNS - 1) })
+    }
+
+    pub fn id(&self) -> usize {
+        self.row << COLUMN_BITS | self.column
+    }
+
+}
+
+fn decode_bits(input: &[char], offset: usize, (low, high): (char, char)) -> Result<usize, SeatError> {
+    input.iter()
+        .enumerate()
+        .try_fold(0, |acc, (index, &character)| match character {
+            c if c == low => Ok(acc << 1),
+            c if c == high => Ok(acc << 1 | 1),
+            _ => Err(SeatError::InvalidCharacter { position: offset + index, character })
+        })
+}
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> FromStr for SeatCode<ROW_BITS, COLUMN_BITS> {
+    type Err = SeatError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let chars = input.chars().collect::<Vec<_>>();
+        if chars.len() != ROW_BITS + COLUMN_BITS {
+            return Err(SeatError::InvalidLength { expected: ROW_BITS + COLUMN_BITS, found: chars.len() })
+        }
+        let row = decode_bits(&chars[..ROW_BITS], 0, ('F', 'B'))?;
+        let column = decode_bits(&chars[ROW_BITS..], ROW_BITS, ('L', 'R'))?;
+        Ok(SeatCode { row, column })
+    }
+}
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> fmt::Display for SeatCode<ROW_BITS, COLUMN_BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (0..ROW_BITS).rev()
+            .map(|bit| if self.row >> bit & 1 == 1 { 'B' } else { 'F' })
+            .chain((0..COLUMN_BITS).rev().map(|bit| if self.column >> bit & 1 == 1 { 'R' } else { 'L' }))
+            .try_for_each(|c| write!(f, "{}", c))
+    }
+}
 
 fn parse_boarding_pass(input: &str) -> usize {
-    let chars = input.chars();
-    let row = chars.clone().take(7)
-        .fold((0, 127), |(front, back), char| {
-            let half = (front + back) / 2;
-            match char {
-                'F' => (front, half),
-                'B' => (half + 1, back),
-                _ => panic!("Invalid input")
-            }
-        });
-    assert!(row.0 == row.1);
-    let column = chars.skip(7).take(3)
-        .fold((0, 7), |(front, back), char| {
-            let half = (front + back) / 2;
-            match char {
-                'L' => (front, half),
-                'R' => (half + 1, back),
-                _ => panic!("Invalid input")
-            }
-        });
-    assert!(column.0 == column.1);
-    row.0 * 8 + column.0
+    input.parse::<BoardingPass>().unwrap().id()
 }
 
 #[aoc(day5, part1)]
@@ -32,23 +99,147 @@ pub fn solve_part_one(input: &str) -> usize {
         .unwrap()
 }
 
+pub struct SeatMap<const ROW_BITS: usize, const COLUMN_BITS: usize> {
+    occupied: Vec<bool>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GapAnalysis<const ROW_BITS: usize, const COLUMN_BITS: usize> {
+    pub front: Vec<SeatCode<ROW_BITS, COLUMN_BITS>>,
+    pub back: Vec<SeatCode<ROW_BITS, COLUMN_BITS>>,
+    pub interior: Vec<SeatCode<ROW_BITS, COLUMN_BITS>>
+}
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> SeatMap<ROW_BITS, COLUMN_BITS> {
+
+    pub fn from_ids<I: IntoIterator<Item = usize>>(ids: I) -> Result<Self, SeatError> {
+        let mut occupied = vec![false; SeatCode::<ROW_BITS, COLUMN_BITS>::SEATS];
+        for id in ids {
+            match occupied.get_mut(id) {
+                Some(seat) => *seat = true,
+                None => return Err(SeatError::OutOfRange(id))
+            }
+        }
+        Ok(SeatMap { occupied })
+    }
+
+    pub fn from_passes(input: &str) -> Result<Self, SeatError> {
+        let ids = input.lines()
+            .map(|line| line.parse::<SeatCode<ROW_BITS, COLUMN_BITS>>().map(|seat| seat.id()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_ids(ids)
+    }
+
+    pub fn is_occupied(&self, seat: SeatCode<ROW_BITS, COLUMN_BITS>) -> bool {
+        self.occupied[seat.id()]
+    }
+
+    pub fn missing(&self) -> Vec<SeatCode<ROW_BITS, COLUMN_BITS>> {
+        self.occupied.iter()
+            .enumerate()
+            .filter(|(_, &full)| !full)
+            .map(|(id, _)| SeatCode::from_id(id).unwrap())
+            .collect()
+    }
+
+    pub fn gaps(&self) -> GapAnalysis<ROW_BITS, COLUMN_BITS> {
+        let first = self.occupied.iter().position(|&full| full);
+        let last = self.occupied.iter().rposition(|&full| full);
+        let mut analysis = GapAnalysis { front: Vec::new(), back: Vec::new(), interior: Vec::new() };
+        for seat in self.missing() {
+            match (first, last) {
+                (Some(first), _) if seat.id() < first => analysis.front.push(seat),
+                (_, Some(last)) if seat.id() > last => analysis.back.push(seat),
+                (Some(_), Some(_)) => analysis.interior.push(seat),
+                _ => analysis.front.push(seat)
+            }
+        }
+        analysis
+    }
+
+    pub fn render(&self) -> String {
+        let interior = self.gaps().interior;
+        let columns = SeatCode::<ROW_BITS, COLUMN_BITS>::COLUMNS;
+        self.occupied.chunks(columns)
+            .enumerate()
+            .map(|(row, seats)| {
+                let seats = seats.iter()
+                    .enumerate()
+                    .map(|(column, &full)| match full {
+                        true => '#',
+                        false if interior.contains(&SeatCode { row, column }) => 'X',
+                        false => '.'
+                    })
+                    .collect::<String>();
+                format!("{:>4} {}\n", row, seats)
+            })
+            .collect()
+    }
+
+}
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> GapAnalysis<ROW_BITS, COLUMN_BITS> {
+
+    fn empty_rows(seats: &[SeatCode<ROW_BITS, COLUMN_BITS>]) -> Vec<usize> {
+        let columns = SeatCode::<ROW_BITS, COLUMN_BITS>::COLUMNS;
+        let mut rows = seats.iter().map(|seat| seat.row).collect::<Vec<_>>();
+        rows.dedup();
+        rows.into_iter()
+            .filter(|&row| seats.iter().filter(|seat| seat.row == row).count() == columns)
+            .collect()
+    }
+
+    pub fn missing_front_rows(&self) -> Vec<usize> {
+        Self::empty_rows(&self.front)
+    }
+
+    pub fn missing_back_rows(&self) -> Vec<usize> {
+        Self::empty_rows(&self.back)
+    }
+
+}
+
+impl <const ROW_BITS: usize, const COLUMN_BITS: usize> fmt::Display for GapAnalysis<ROW_BITS, COLUMN_BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |seats: &[SeatCode<ROW_BITS, COLUMN_BITS>], rows: Vec<usize>| {
+            let partial = seats.iter()
+                .filter(|seat| !rows.contains(&seat.row))
+                .map(|seat| format!("{} ({})", seat.id(), seat))
+                .collect::<Vec<_>>();
+            match partial.is_empty() {
+                true => format!("{} empty rows", rows.len()),
+                false => format!("{} empty rows and {} seats: {}", rows.len(), partial.len(), partial.join(", "))
+            }
+        };
+        writeln!(f, "front: {}", describe(&self.front, self.missing_front_rows()))?;
+        writeln!(f, "back: {}", describe(&self.back, self.missing_back_rows()))?;
+        writeln!(f, "interior gaps: {}", self.interior.len())?;
+        for seat in self.interior.iter() {
+            writeln!(f, "  seat {} ({}) row {} column {}", seat.id(), seat, seat.row, seat.column)?;
+        }
+        Ok(())
+    }
+}
+
 #[aoc(day5, part2)]
 pub fn solve_part_two(input: &str) -> usize {
-    let mut seats = [false; 127 * 8];
-    input.lines()
-        .map(parse_boarding_pass)
-        .for_each(|seat| seats[seat] = true);
-    seats.iter()
-        .enumerate()
-        .skip_while(|(_, &full)| !full)
-        .find(|(_, &full)| !full)
+    SeatMap::<7, 3>::from_passes(input)
         .unwrap()
-        .0
+        .gaps()
+        .interior[0]
+        .id()
+}
+
+#[aoc(day5, part2, Map)]
+pub fn render_part_two(input: &str) -> Result<String, SeatError> {
+    let map = SeatMap::<7, 3>::from_passes(input)?;
+    Ok(format!("\n{}{}", map.render(), map.gaps()))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use indoc::indoc;
 
     #[test]
     fn it_solves_part_one() {
@@ -57,4 +248,54 @@ mod test {
         assert_eq!(parse_boarding_pass("BBFFBBFRLL"), 820);
     }
 
+    #[test]
+    fn it_encodes_seat_codes() {
+        let seat = BoardingPass::from_id(567).unwrap();
+        assert_eq!(seat, BoardingPass::new(70, 7).unwrap());
+        assert_eq!(seat.to_string(), "BFFFBBFRRR");
+        assert_eq!(SeatCode::<2, 2>::from_id(9).unwrap().to_string(), "BFLR");
+        assert_eq!("BFLR".parse::<SeatCode<2, 2>>().unwrap().id(), 9);
+    }
+
+    #[test]
+    fn it_analyses_gaps() {
+        let ids = (11..=21).chain(23..=26).chain(28..=29);
+        let map = SeatMap::<3, 2>::from_ids(ids).unwrap();
+        let gaps = map.gaps();
+        assert_eq!(gaps.interior, vec![SeatCode::from_id(22).unwrap(), SeatCode::from_id(27).unwrap()]);
+        assert_eq!(gaps.missing_front_rows(), vec![0, 1]);
+        assert_eq!(gaps.front.len(), 11);
+        assert!(gaps.missing_back_rows().is_empty());
+        assert_eq!(gaps.back.len(), 2);
+        assert_eq!(map.render(), "   0 ....\n   1 ....\n   2 ...#\n   3 ####\n   4 ####\n   5 ##X#\n   6 ###X\n   7 ##..\n");
+        assert_eq!(map.missing().len(), 15);
+        assert_eq!(gaps.to_string(), indoc! {"
+            front: 2 empty rows and 3 seats: 8 (FBFLL), 9 (FBFLR), 10 (FBFRL)
+            back: 0 empty rows and 2 seats: 30 (BBBRL), 31 (BBBRR)
+            interior gaps: 2
+              seat 22 (BFBRL) row 5 column 2
+              seat 27 (BBFRR) row 6 column 3
+        "});
+        assert!(SeatMap::<3, 2>::from_ids(vec![32]).is_err());
+    }
+
+    #[test]
+    fn it_solves_part_two() {
+        let input = (100..900).filter(|&id| id != 555)
+            .map(|id| BoardingPass::from_id(id).unwrap().to_string() + "\n")
+            .collect::<String>();
+        assert_eq!(solve_part_two(&input), 555);
+        assert_eq!(solve_part_two(&(input + "BBBBBBBRRR\n")), 555);
+        assert!(SeatMap::<7, 3>::from_passes("BBBBBBBRRRR").is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_seat_codes() {
+        assert_eq!("BFFFBBFRR".parse::<BoardingPass>(), Err(SeatError::InvalidLength { expected: 10, found: 9 }));
+        assert_eq!("BFFFBXFRRR".parse::<BoardingPass>(), Err(SeatError::InvalidCharacter { position: 5, character: 'X' }));
+        assert_eq!("BFFFBBFRRB".parse::<BoardingPass>(), Err(SeatError::InvalidCharacter { position: 9, character: 'B' }));
+        assert_eq!(BoardingPass::from_id(1024), Err(SeatError::OutOfRange(1024)));
+        assert_eq!(BoardingPass::new(128, 0), Err(SeatError::OutOfRange(1024)));
+    }
+
 }
@@ -1,59 +1,260 @@
-use std::collections::HashSet;
+use std::str::FromStr;
 
-#[aoc(day6, part1)]
-pub fn solve_part_one(input: &str) -> usize {
-    let mut output = Vec::new();
-    output.push(HashSet::new());
-    let lines = input.lines();
-    
-    for line in lines {
-        if line.is_empty() {
-            output.push(HashSet::new());
-            continue;
+use nom::{IResult, branch::alt, bytes::complete::tag, character::complete::{char, digit1, space0, space1}, combinator::{all_consuming, map, map_res, opt, value}, multi::fold_many0, sequence::{delimited, pair, preceded, tuple}};
+
+use serde::Serialize;
+
+use crate::groups::groups;
+
+const QUESTIONS: usize = 26;
+const ALL_QUESTIONS: u32 = (1 << QUESTIONS) - 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub people: Vec<u32>
+}
+
+impl Group {
+
+    pub fn from_lines(lines: &[String]) -> Self {
+        let people = lines.iter()
+            .map(|line| {
+                line.bytes()
+                    .filter(|byte| byte.is_ascii_lowercase())
+                    .fold(0, |mask, byte| mask | 1 << (byte - b'a'))
+            })
+            .collect();
+        Group { people }
+    }
+
+    pub fn size(&self) -> usize {
+        self.people.len()
+    }
+
+    pub fn yes_count(&self, question: usize) -> usize {
+        self.people.iter()
+            .filter(|&&person| person >> question & 1 == 1)
+            .count()
+    }
+
+    fn questions_where<F: Fn(usize) -> bool>(&self, predicate: F) -> u32 {
+        (0..QUESTIONS)
+            .filter(|&question| predicate(self.yes_count(question)))
+            .fold(0, |mask, question| mask | 1 << question)
+    }
+
+}
+
+pub fn parse_groups(input: &str) -> Vec<Group> {
+    groups(input)
+        .map(|group| Group::from_lines(&group))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Any,
+    All,
+    None,
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>)
+}
+
+impl Query {
+
+    pub const PART_ONE: Query = Query::Any;
+    pub const PART_TWO: Query = Query::All;
+
+    pub fn evaluate(&self, group: &Group) -> u32 {
+        match self {
+            Query::Any => group.people.iter().fold(0, |mask, person| mask | person),
+            Query::All => group.people.iter().fold(ALL_QUESTIONS, |mask, person| mask & person),
+            Query::None => !Query::Any.evaluate(group) & ALL_QUESTIONS,
+            Query::Exactly(n) => group.questions_where(|count| count == *n),
+            Query::AtLeast(n) => group.questions_where(|count| count >= *n),
+            Query::AtMost(n) => group.questions_where(|count| count <= *n),
+            Query::Not(query) => !query.evaluate(group) & ALL_QUESTIONS,
+            Query::And(a, b) => a.evaluate(group) & b.evaluate(group),
+            Query::Or(a, b) => a.evaluate(group) | b.evaluate(group)
         }
-        let current = output.last_mut().unwrap();
-        line.chars().for_each(|x| { current.insert(x); });
     }
 
-    if output.last().unwrap().is_empty() { output.pop(); }
-    
-    output.iter()
-        .map(HashSet::len)
-        .sum()
+    pub fn count(&self, groups: &[Group]) -> usize {
+        groups.iter()
+            .map(|group| self.evaluate(group).count_ones() as usize)
+            .sum()
+    }
+
 }
 
-#[aoc(day6, part2)]
-pub fn solve_part_two(input: &str) -> usize {
-    let mut output = Vec::new();
-    output.push(HashSet::new());
-    let lines = input.lines();
-    let mut is_new = true;
-    
-    for line in lines {
-        if line.is_empty() {
-            output.push(HashSet::new());
-            is_new = true;
-            continue;
+fn parse_number(input: &str) -> IResult<&str, usize> {
+    let (rest, (_, number, _)) = tuple((
+        space1,
+        map_res(digit1, FromStr::from_str),
+        opt(preceded(space1, alt((tag("people"), tag("person"))))),
+    ))(input)?;
+    Ok((rest, number))
+}
+
+fn parse_atom(input: &str) -> IResult<&str, Query> {
+    alt((
+        map(preceded(tag("exactly"), parse_number), Query::Exactly),
+        map(preceded(tag("at least"), parse_number), Query::AtLeast),
+        map(preceded(tag("at most"), parse_number), Query::AtMost),
+        value(Query::Any, tag("any")),
+        value(Query::All, tag("all")),
+        value(Query::None, tag("none")),
+    ))(input)
+}
+
+fn parse_factor(input: &str) -> IResult<&str, Query> {
+    alt((
+        map(preceded(pair(tag("not"), space1), parse_factor), |query| Query::Not(Box::new(query))),
+        delimited(pair(char('('), space0), parse_expression, pair(space0, char(')'))),
+        parse_atom,
+    ))(input)
+}
+
+fn parse_term(input: &str) -> IResult<&str, Query> {
+    let (rest, first) = parse_factor(input)?;
+    fold_many0(
+        preceded(tuple((space1, tag("and"), space1)), parse_factor),
+        first,
+        |a, b| Query::And(Box::new(a), Box::new(b))
+    )(rest)
+}
+
+fn parse_expression(input: &str) -> IResult<&str, Query> {
+    let (rest, first) = parse_term(input)?;
+    fold_many0(
+        preceded(tuple((space1, tag("or"), space1)), parse_term),
+        first,
+        |a, b| Query::Or(Box::new(a), Box::new(b))
+    )(rest)
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        all_consuming(parse_expression)(input.trim())
+            .map(|(_, query)| query)
+            .map_err(|_| format!("invalid query {:?}", input))
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct QuestionStats {
+    pub question: char,
+    pub yes: usize,
+    pub percentage: f64
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct GroupStats {
+    pub group: usize,
+    pub size: usize,
+    pub questions: Vec<QuestionStats>
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Popularity {
+    pub question: char,
+    pub people: usize,
+    pub groups: usize
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Statistics {
+    pub groups: Vec<GroupStats>,
+    pub popularity: Vec<Popularity>
+}
+
+fn question_name(question: usize) -> char {
+    (b'a' + question as u8) as char
+}
+
+impl Statistics {
+
+    pub fn new(groups: &[Group]) -> Self {
+        let group_stats = groups.iter()
+            .enumerate()
+            .map(|(index, group)| {
+                let questions = (0..QUESTIONS)
+                    .map(|question| (question, group.yes_count(question)))
+                    .filter(|&(_, yes)| yes > 0)
+                    .map(|(question, yes)| QuestionStats {
+                        question: question_name(question),
+                        yes,
+                        percentage: 100.0 * yes as f64 / group.size() as f64
+                    })
+                    .collect();
+                GroupStats { group: index + 1, size: group.size(), questions }
+            })
+            .collect();
+        let popularity = (0..QUESTIONS)
+            .map(|question| Popularity {
+                question: question_name(question),
+                people: groups.iter().map(|group| group.yes_count(question)).sum(),
+                groups: groups.iter().filter(|group| group.yes_count(question) > 0).count()
+            })
+            .collect();
+        Statistics { groups: group_stats, popularity }
+    }
+
+    pub fn groups_to_csv(&self) -> String {
+        let mut output = String::from("group,size,question,yes,percentage\n");
+        for group in self.groups.iter() {
+            for question in group.questions.iter() {
+                output.push_str(&format!(
+                    "{},{},{},{},{:.1}\n", group.group, group.size, question.question, question.yes, question.percentage
+                ));
+            }
         }
-        let current = output.pop().unwrap();
-        let chars: HashSet<char> = line.chars().collect();
-        if is_new {
-            output.push(chars);
-            is_new = false;
-        } else {
-            let intersection: HashSet<char> = current.intersection(&chars)
-                .map(|x| *x)
-                .collect(); 
-            output.push(intersection);
+        output
+    }
+
+    pub fn popularity_to_csv(&self) -> String {
+        let mut output = String::from("question,people,groups\n");
+        for popularity in self.popularity.iter() {
+            output.push_str(&format!("{},{},{}\n", popularity.question, popularity.people, popularity.groups));
         }
-        
+        output
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
     }
 
-    if output.last().unwrap().is_empty() { output.pop(); }
-    
-    output.iter()
-        .map(HashSet::len)
-        .sum()
+}
+
+#[aoc(day6, part1)]
+pub fn solve_part_one(input: &str) -> usize {
+    Query::PART_ONE.count(&parse_groups(input))
+}
+
+#[aoc(day6, part2)]
+pub fn solve_part_two(input: &str) -> usize {
+    Query::PART_TWO.count(&parse_groups(input))
+}
+
+#[aoc(day6, part1, Csv)]
+pub fn statistics_csv(input: &str) -> String {
+    format!("\n{}", Statistics::new(&parse_groups(input)).groups_to_csv())
+}
+
+#[aoc(day6, part1, PopularityCsv)]
+pub fn popularity_csv(input: &str) -> String {
+    format!("\n{}", Statistics::new(&parse_groups(input)).popularity_to_csv())
+}
+
+#[aoc(day6, part1, Json)]
+pub fn statistics_json(input: &str) -> String {
+    Statistics::new(&parse_groups(input)).to_json()
 }
 
 #[cfg(test)]
@@ -89,4 +290,55 @@ mod test {
         assert_eq!(solve_part_two(&INPUT), 6);
     }
 
+    #[test]
+    fn it_parses_queries() {
+        assert_eq!("any".parse(), Ok(Query::PART_ONE));
+        assert_eq!("exactly 2 people".parse(), Ok(Query::Exactly(2)));
+        assert_eq!("at least 1 person".parse(), Ok(Query::AtLeast(1)));
+        assert_eq!(
+            "not all and (at most 2 or none)".parse(),
+            Ok(Query::And(
+                Box::new(Query::Not(Box::new(Query::All))),
+                Box::new(Query::Or(Box::new(Query::AtMost(2)), Box::new(Query::None)))
+            ))
+        );
+        assert!("exactly".parse::<Query>().is_err());
+        assert!("any or".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn it_evaluates_queries() {
+        let groups = parse_groups(INPUT);
+        let count = |query: &str| query.parse::<Query>().unwrap().count(&groups);
+        assert_eq!(count("any"), 11);
+        assert_eq!(count("all"), 6);
+        assert_eq!(count("at least 1 person"), 11);
+        assert_eq!(count("exactly 1 person"), 9);
+        assert_eq!(count("any and not all"), 5);
+        assert_eq!(count("none"), 5 * 26 - 11);
+        assert_eq!(groups[2].size(), 2);
+        assert_eq!(groups[2].yes_count(0), 2);
+    }
+
+    #[test]
+    fn it_exports_statistics() {
+        let statistics = Statistics::new(&parse_groups(INPUT));
+        assert_eq!(statistics.groups[2], GroupStats { group: 3, size: 2, questions: vec![
+            QuestionStats { question: 'a', yes: 2, percentage: 100.0 },
+            QuestionStats { question: 'b', yes: 1, percentage: 50.0 },
+            QuestionStats { question: 'c', yes: 1, percentage: 50.0 },
+        ] });
+        assert_eq!(statistics.popularity[0], Popularity { question: 'a', people: 8, groups: 4 });
+        assert!(statistics.groups_to_csv().starts_with("group,size,question,yes,percentage\n1,1,a,1,100.0\n"));
+        assert!(statistics.groups_to_csv().contains("\n3,2,b,1,50.0\n"));
+        assert!(statistics.popularity_to_csv().starts_with("question,people,groups\na,8,4\nb,4,4\nc,3,3\nd,0,0\n"));
+
+        assert_eq!(statistics_csv(INPUT), format!("\n{}", statistics.groups_to_csv()));
+        assert_eq!(popularity_csv(INPUT), format!("\n{}", statistics.popularity_to_csv()));
+
+        let json: serde_json::Value = serde_json::from_str(&statistics.to_json()).unwrap();
+        assert_eq!(json["groups"][3]["size"], 4);
+        assert_eq!(json["popularity"][1]["people"], 4);
+    }
+
 }
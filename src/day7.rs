@@ -1,5 +1,5 @@
-use std::{collections::HashSet, str::FromStr, collections::HashMap};
-use nom::{IResult, bytes::complete::tag, bytes::complete::take_until, character::complete::digit1, character::complete::newline, combinator::map_res, combinator::opt, multi::many1, multi::separated_list1, character::complete::space1};
+use std::{cell::RefCell, collections::BTreeMap, collections::HashSet, fmt, str::FromStr, collections::HashMap};
+use nom::{IResult, bytes::complete::tag, bytes::complete::take_until, character::complete::digit1, combinator::map_res, multi::separated_list1, character::complete::space1};
 use nom::sequence::tuple;
 use nom::branch::alt;
 
@@ -30,70 +30,361 @@ fn parse_bag(input: &str) -> IResult<&str, Option<Vec<(String, usize)>>> {
     }
 }
 
-type Bag = (String, Vec<(String, usize)>);
+type Contents = Vec<(String, usize)>;
 
-#[aoc_generator(day7)]
-pub fn parse_bags(input: &str) -> Vec<Bag> {
-    let mut bags = Vec::new();
-    let result: IResult<_, Vec<(&str,&str,Option<Vec<(String, usize)>>, _, _, Option<_>)>> = many1(tuple((
+type Bag = (String, Contents);
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    Parse { line: usize, content: String },
+    Duplicate { color: String, first: usize, line: usize },
+    Undefined { color: String, line: usize },
+    Cycle(Vec<String>)
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Parse { line, content } => write!(f, "line {}: could not parse rule {:?}", line, content),
+            RuleError::Duplicate { color, first, line } => write!(f, "line {}: {} bags already defined on line {}", line, color, first),
+            RuleError::Undefined { color, line } => write!(f, "line {}: {} bags are never defined", line, color),
+            RuleError::Cycle(path) => write!(f, "cycle: {}", path.join(" -> "))
+        }
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Bag> {
+    let result: IResult<&str, (&str, &str, Option<Contents>, &str)> = tuple((
         take_until(" bags contain "),
         tag(" bags contain "),
         parse_bag,
-        take_until("."),
-        tag("."),
-        opt(newline)
-    )))(input);
+        tag(".")
+    ))(line);
     match result {
-        Ok((_, vec)) => vec.iter().for_each(|(color, _, types, _, _, _)| {
-            let mut child_colors = Vec::new();
-            match types {
-                None => {},
-                Some(types) => types.iter()
-                    .for_each(|(color, count)| child_colors.push((color.to_string(), *count)))
-            };
-            bags.push((color.to_string(), child_colors));
-        }),
-        Err(_) => {}
-    };
-    bags
+        Ok(("", (color, _, types, _))) => Some((color.to_string(), types.unwrap_or_default())),
+        _ => None
+    }
 }
 
-fn find_matching(input: &[Bag], target: &str) -> Vec<String> {
-    let direct = input.iter()
-        .filter(|(_, children)| children.iter().any(|(color, _)| color == target))
-        .map(|(color, _)| color.to_string())
-        .collect::<Vec<_>>();
-    let nested = direct.iter()
-        .flat_map(|color| find_matching(input, color))
-        .collect::<Vec<_>>();
-    [direct, nested].concat()
+pub fn parse_bags(input: &str) -> (Vec<Bag>, Vec<RuleError>) {
+    let mut bags = Vec::new();
+    let mut errors = Vec::new();
+    let mut defined: HashMap<String, usize> = HashMap::new();
+    let lines = input.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    for (line, content) in lines {
+        match parse_rule(content) {
+            None => errors.push(RuleError::Parse { line, content: content.to_string() }),
+            Some((color, children)) => match defined.get(&color) {
+                Some(&first) => errors.push(RuleError::Duplicate { color, first, line }),
+                None => {
+                    defined.insert(color.to_string(), line);
+                    bags.push((line, (color, children)));
+                }
+            }
+        }
+    }
+    let mut reported = HashSet::new();
+    for (line, (_, children)) in bags.iter() {
+        for (color, _) in children.iter() {
+            if !defined.contains_key(color) && reported.insert(color) {
+                errors.push(RuleError::Undefined { color: color.to_string(), line: *line });
+            }
+        }
+    }
+    (bags.into_iter().map(|(_, bag)| bag).collect(), errors)
+}
+
+pub fn validate(input: &str) -> Vec<RuleError> {
+    BagGraph::from_input(input).err().unwrap_or_default()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ContentsNode<'a> {
+    pub color: &'a str,
+    pub count: usize,
+    pub multiplicity: usize,
+    pub total: usize,
+    pub children: Vec<ContentsNode<'a>>
 }
 
-fn count_needed(input: &HashMap<String, &Vec<(String, usize)>>, target: &str) -> usize {
-    match input.get(target) {
-        None => 0,
-        Some(vec) =>
-            vec.iter()
-                .map(|(color, count)| { count * count_needed(input, color)})
-                .sum::<usize>() + 1
+impl <'a> ContentsNode<'a> {
+
+    fn render(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        match depth {
+            0 => writeln!(f, "{} [{} inside]", self.color, self.total)?,
+            _ => writeln!(
+                f, "{}{} {} [{} total, {} inside each]",
+                "  ".repeat(depth), self.count, self.color, self.multiplicity, self.total
+            )?
+        };
+        self.children.iter().try_for_each(|child| child.render(f, depth + 1))
+    }
+
+}
+
+impl <'a> fmt::Display for ContentsNode<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.render(f, 0)
     }
 }
 
+#[derive(Debug, Default)]
+pub struct BagGraph {
+    colors: Vec<String>,
+    ids: HashMap<String, usize>,
+    contents: Vec<Vec<(usize, usize)>>,
+    containers: Vec<Vec<(usize, usize)>>,
+    container_cache: RefCell<Vec<Option<Vec<usize>>>>,
+    count_cache: RefCell<Vec<Option<usize>>>
+}
+
+impl BagGraph {
+
+    pub fn new(bags: &[Bag]) -> Result<Self, Vec<RuleError>> {
+        let mut graph = BagGraph::default();
+        for (color, children) in bags.iter() {
+            let id = graph.intern(color);
+            for (child, count) in children.iter() {
+                let child = graph.intern(child);
+                graph.contents[id].push((child, *count));
+                graph.containers[child].push((id, *count));
+            }
+        }
+        let cycles = graph.cycles()
+            .into_iter()
+            .map(|path| RuleError::Cycle(path.iter().map(|color| color.to_string()).collect()))
+            .collect::<Vec<_>>();
+        match cycles.is_empty() {
+            true => Ok(graph),
+            false => Err(cycles)
+        }
+    }
+
+    pub fn from_input(input: &str) -> Result<Self, Vec<RuleError>> {
+        let (bags, mut errors) = parse_bags(input);
+        match BagGraph::new(&bags) {
+            Ok(graph) if errors.is_empty() => Ok(graph),
+            Ok(_) => Err(errors),
+            Err(cycles) => {
+                errors.extend(cycles);
+                Err(errors)
+            }
+        }
+    }
+
+    fn intern(&mut self, color: &str) -> usize {
+        match self.ids.get(color) {
+            Some(&id) => id,
+            None => {
+                let id = self.colors.len();
+                self.colors.push(color.to_string());
+                self.ids.insert(color.to_string(), id);
+                self.contents.push(Vec::new());
+                self.containers.push(Vec::new());
+                self.container_cache.borrow_mut().push(None);
+                self.count_cache.borrow_mut().push(None);
+                id
+            }
+        }
+    }
+
+    pub fn id(&self, color: &str) -> Option<usize> {
+        self.ids.get(color).copied()
+    }
+
+    pub fn color(&self, id: usize) -> &str {
+        &self.colors[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn contents(&self, id: usize) -> &[(usize, usize)] {
+        &self.contents[id]
+    }
+
+    pub fn containers(&self, id: usize) -> &[(usize, usize)] {
+        &self.containers[id]
+    }
+
+    fn visit(&self, id: usize, state: &mut Vec<u8>, stack: &mut Vec<usize>, cycles: &mut Vec<Vec<usize>>) {
+        state[id] = 1;
+        stack.push(id);
+        for &(child, _) in self.contents[id].iter() {
+            match state[child] {
+                0 => self.visit(child, state, stack, cycles),
+                1 => {
+                    let start = stack.iter().position(|&id| id == child).unwrap();
+                    cycles.push([&stack[start..], &[child]].concat());
+                },
+                _ => {}
+            }
+        }
+        stack.pop();
+        state[id] = 2;
+    }
+
+    pub fn cycles(&self) -> Vec<Vec<&str>> {
+        let mut state = vec![0; self.len()];
+        let mut cycles = Vec::new();
+        for id in 0..self.len() {
+            if state[id] == 0 {
+                self.visit(id, &mut state, &mut Vec::new(), &mut cycles);
+            }
+        }
+        cycles.into_iter()
+            .map(|path| path.into_iter().map(|id| self.color(id)).collect())
+            .collect()
+    }
+
+    fn ancestors(&self, id: usize) -> Vec<usize> {
+        let cached = self.container_cache.borrow()[id].clone();
+        if let Some(ancestors) = cached {
+            return ancestors;
+        }
+        let mut ancestors = Vec::new();
+        for &(parent, _) in self.containers[id].iter() {
+            ancestors.push(parent);
+            ancestors.extend(self.ancestors(parent));
+        }
+        ancestors.sort_unstable();
+        ancestors.dedup();
+        self.container_cache.borrow_mut()[id] = Some(ancestors.clone());
+        ancestors
+    }
+
+    fn total(&self, id: usize) -> usize {
+        if let Some(count) = self.count_cache.borrow()[id] {
+            return count;
+        }
+        let count = self.contents[id].iter()
+            .map(|&(child, count)| count * (self.total(child) + 1))
+            .sum();
+        self.count_cache.borrow_mut()[id] = Some(count);
+        count
+    }
+
+    fn descendants(&self, id: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.len()];
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            for &(child, _) in self.contents[id].iter() {
+                if !seen[child] {
+                    seen[child] = true;
+                    pending.push(child);
+                }
+            }
+        }
+        (0..self.len()).filter(|&id| seen[id]).collect()
+    }
+
+    pub fn to_dot(&self, highlight: Option<&str>) -> String {
+        let mut fills = vec![None; self.len()];
+        if let Some(id) = highlight.and_then(|color| self.id(color)) {
+            self.ancestors(id).into_iter().for_each(|id| fills[id] = Some("lightblue"));
+            self.descendants(id).into_iter().for_each(|id| fills[id] = Some("palegreen"));
+            fills[id] = Some("gold");
+        }
+        let mut output = String::from("digraph bags {\n");
+        for (id, fill) in fills.into_iter().enumerate() {
+            match fill {
+                Some(fill) => output.push_str(&format!("    {:?} [style=filled, fillcolor={}];\n", self.color(id), fill)),
+                None => output.push_str(&format!("    {:?};\n", self.color(id)))
+            }
+        }
+        for id in 0..self.len() {
+            for &(child, count) in self.contents[id].iter() {
+                output.push_str(&format!(
+                    "    {:?} -> {:?} [label={}, weight={}];\n", self.color(id), self.color(child), count, count
+                ));
+            }
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    pub fn to_json(&self) -> String {
+        let adjacency = (0..self.len())
+            .map(|id| {
+                let contents = self.contents[id].iter()
+                    .map(|&(child, count)| (self.color(child), count))
+                    .collect::<BTreeMap<_, _>>();
+                (self.color(id), contents)
+            })
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string_pretty(&adjacency).unwrap()
+    }
+
+    fn expand(&self, id: usize, count: usize, multiplicity: usize) -> ContentsNode<'_> {
+        let children = self.contents[id].iter()
+            .map(|&(child, count)| self.expand(child, count, multiplicity * count))
+            .collect();
+        ContentsNode { color: self.color(id), count, multiplicity, total: self.total(id), children }
+    }
+
+    pub fn contents_tree(&self, color: &str) -> Option<ContentsNode<'_>> {
+        self.id(color).map(|id| self.expand(id, 1, 1))
+    }
+
+    pub fn containers_of(&self, color: &str) -> Option<Vec<&str>> {
+        let id = self.id(color)?;
+        Some(self.ancestors(id).into_iter().map(|id| self.color(id)).collect())
+    }
+
+    pub fn contents_count(&self, color: &str) -> Option<usize> {
+        self.id(color).map(|id| self.total(id))
+    }
+
+}
+
+#[aoc_generator(day7)]
+pub fn input_generator(input: &str) -> BagGraph {
+    let (bags, errors) = parse_bags(input);
+    let mut errors = errors.into_iter()
+        .filter(|error| !matches!(error, RuleError::Undefined { .. }))
+        .collect::<Vec<_>>();
+    match BagGraph::new(&bags) {
+        Ok(graph) if errors.is_empty() => return graph,
+        Ok(_) => {},
+        Err(cycles) => errors.extend(cycles)
+    };
+    let errors = errors.iter().map(|error| error.to_string()).collect::<Vec<_>>();
+    panic!("invalid bag rules:\n{}", errors.join("\n"))
+}
+
 const TARGET: &str = "shiny gold";
 
 #[aoc(day7, part1)]
-pub fn solve_part_one(input: &[Bag]) -> usize {
-    find_matching(input, TARGET)
-        .iter()
-        .collect::<HashSet<_>>()
-        .len()
+pub fn solve_part_one(input: &BagGraph) -> usize {
+    input.containers_of(TARGET).map_or(0, |containers| containers.len())
 }
 
 #[aoc(day7, part2)]
-pub fn solve_part_two(input: &[Bag]) -> usize {
-    let mut hashmap = HashMap::new();
-    input.iter().for_each(|(color, children)| { hashmap.insert(color.to_string(), children); });
-    count_needed(&hashmap, TARGET) - 1
+pub fn solve_part_two(input: &BagGraph) -> usize {
+    input.contents_count(TARGET).unwrap_or(0)
+}
+
+#[aoc(day7, part2, Tree)]
+pub fn render_tree(input: &BagGraph) -> String {
+    input.contents_tree(TARGET).map_or(String::new(), |tree| format!("\n{}", tree))
+}
+
+#[aoc(day7, part1, Dot)]
+pub fn export_dot(input: &BagGraph) -> String {
+    format!("\n{}", input.to_dot(Some(TARGET)))
+}
+
+#[aoc(day7, part1, Json)]
+pub fn export_json(input: &BagGraph) -> String {
+    format!("\n{}", input.to_json())
 }
 
 #[cfg(test)]
@@ -125,16 +416,105 @@ mod test {
 
     #[test]
     fn it_solves_part_one() {
-        let input = parse_bags(INPUT);
+        let input = input_generator(INPUT);
         assert_eq!(solve_part_one(&input), 4);
     }
     
     #[test]
     fn it_solves_part_two() {
-        let input = parse_bags(INPUT);
-        let input_two = parse_bags(INPUT_TWO);
+        let input = input_generator(INPUT);
+        let input_two = input_generator(INPUT_TWO);
         assert_eq!(solve_part_two(&input), 32);
         assert_eq!(solve_part_two(&input_two), 126);
     }
 
+    #[test]
+    fn it_queries_any_color() {
+        let graph = input_generator(INPUT);
+        assert_eq!(graph.len(), 9);
+        assert_eq!(graph.containers_of("muted yellow"), Some(vec!["light red", "dark orange"]));
+        assert_eq!(graph.containers_of("light red"), Some(vec![]));
+        assert_eq!(graph.containers_of("faded blue").map(|colors| colors.len()), Some(7));
+        assert_eq!(graph.contents_count("dark olive"), Some(7));
+        assert_eq!(graph.contents_count("faded blue"), Some(0));
+        assert_eq!(graph.contents_count("light red"), Some(186));
+        assert_eq!(graph.contents_count("neon pink"), None);
+        assert_eq!(graph.containers_of("neon pink"), None);
+    }
+
+    #[test]
+    fn it_expands_contents() {
+        let graph = input_generator(INPUT_TWO);
+        let tree = graph.contents_tree(TARGET).unwrap();
+        assert_eq!(tree.total, 126);
+        assert_eq!(tree.children[0].children[0].multiplicity, 4);
+        assert_eq!(tree.children[0].children[0].total, 30);
+        assert_eq!(tree.to_string(), indoc! {"
+            shiny gold [126 inside]
+              2 dark red [2 total, 62 inside each]
+                2 dark orange [4 total, 30 inside each]
+                  2 dark yellow [8 total, 14 inside each]
+                    2 dark green [16 total, 6 inside each]
+                      2 dark blue [32 total, 2 inside each]
+                        2 dark violet [64 total, 0 inside each]
+        "});
+        assert_eq!(graph.contents_tree("neon pink"), None);
+
+        let graph = input_generator(INPUT);
+        let tree = graph.contents_tree("dark olive").unwrap();
+        assert_eq!(tree.children.iter().map(|child| child.multiplicity).collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn it_exports_the_graph() {
+        let graph = input_generator(INPUT);
+        let dot = graph.to_dot(Some("muted yellow"));
+        assert!(dot.starts_with("digraph bags {\n"));
+        assert!(dot.contains("    \"muted yellow\" [style=filled, fillcolor=gold];\n"));
+        assert!(dot.contains("    \"dark orange\" [style=filled, fillcolor=lightblue];\n"));
+        assert!(dot.contains("    \"dotted black\" [style=filled, fillcolor=palegreen];\n"));
+        assert!(dot.contains("    \"bright white\";\n"));
+        assert!(dot.contains("    \"muted yellow\" -> \"faded blue\" [label=9, weight=9];\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["light red"]["muted yellow"], 2);
+        assert_eq!(json["faded blue"], serde_json::json!({}));
+        assert_eq!(json.as_object().unwrap().len(), 9);
+    }
+
+    #[test]
+    fn it_treats_undefined_colors_as_empty_bags() {
+        let input = "shiny gold bags contain 2 neon pink bags, 1 dark red bag.\ndark red bags contain 3 neon pink bags.\n";
+        assert_eq!(validate(input).len(), 1);
+        let graph = input_generator(input);
+        assert_eq!(solve_part_two(&graph), 3 + 3);
+        assert_eq!(graph.containers_of("neon pink"), Some(vec!["shiny gold", "dark red"]));
+    }
+
+    #[test]
+    fn it_validates_rules() {
+        assert_eq!(validate(INPUT), vec![]);
+        let input = indoc! {"
+            light red bags contain 1 bright white bag, 2 muted yellow bags.
+            bright white bags contain 1 shiny gold bag.
+            muted yellow bags contain lots of bags.
+            shiny gold bags contain 2 light red bags, 1 neon pink bag.
+
+            light red bags contain no other bags.
+        "};
+        assert_eq!(validate(input), vec![
+            RuleError::Parse { line: 3, content: "muted yellow bags contain lots of bags.".to_string() },
+            RuleError::Duplicate { color: "light red".to_string(), first: 1, line: 6 },
+            RuleError::Undefined { color: "muted yellow".to_string(), line: 1 },
+            RuleError::Undefined { color: "neon pink".to_string(), line: 4 },
+            RuleError::Cycle(vec!["light red".to_string(), "bright white".to_string(), "shiny gold".to_string(), "light red".to_string()]),
+        ]);
+        assert_eq!(BagGraph::new(&parse_bags(input).0).err().map(|errors| errors.len()), Some(1));
+        assert_eq!(
+            validate(input)[4].to_string(),
+            "cycle: light red -> bright white -> shiny gold -> light red"
+        );
+    }
+
 }
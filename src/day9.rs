@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{collections::HashMap, collections::VecDeque, io, io::BufRead, io::Write, ops::RangeInclusive, str::FromStr};
 
 #[aoc_generator(day9)]
 pub fn input_generator(input: &str) -> Vec<usize> {
@@ -8,51 +8,164 @@ pub fn input_generator(input: &str) -> Vec<usize> {
         .collect()
 }
 
-fn find_invalid_value(input: &[usize], preamble: usize) -> usize {
-    let mut upper = preamble;
-    while upper < input.len() - 1 {
-        let lower = upper - preamble;
-        let next = input[upper];
-        let is_valid  = input[lower..upper].iter()
-            .any(|x| {
-                input[lower..upper].iter()
-                    .filter(|&y| y != x)
-                    .any(|&y| x + y == next)
-            });
-        if !is_valid { return next }
-        upper += 1;
+pub fn read_numbers<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<usize>> {
+    reader.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let line = line?;
+            line.trim().parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {:?} is not a number", index + 1, line))
+            })
+        })
+}
+
+pub const PREAMBLE: usize = 25;
+
+pub struct XmasWindow {
+    size: usize,
+    values: VecDeque<usize>,
+    sums: HashMap<usize, usize>
+}
+
+impl XmasWindow {
+
+    pub fn new(size: usize) -> Result<Self, String> {
+        match size {
+            0 => Err("Preamble must contain at least one number".to_owned()),
+            _ => Ok(XmasWindow { size, values: VecDeque::with_capacity(size), sums: HashMap::new() })
+        }
     }
-    panic!("No value found")
-}
-
-fn find_continguous(input: &[usize], preabmle: usize) -> usize {
-    let target = find_invalid_value(input, preabmle);
-    let mut lower = 0;
-    while lower < input.len() - 2 {
-        let mut upper = lower + 1;
-        let mut sum = input[lower];
-        while sum < target && upper < input.len() {
-            sum += input[upper];
-            if sum == target {
-                let min = input[lower..=upper].iter().min().unwrap();
-                let max = input[lower..=upper].iter().max().unwrap();
-                return min + max;
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.size
+    }
+
+    pub fn is_valid(&self, value: usize) -> bool {
+        self.sums.contains_key(&value)
+    }
+
+    pub fn check(&mut self, value: usize) -> bool {
+        let is_valid = !self.is_full() || self.is_valid(value);
+        self.push(value);
+        is_valid
+    }
+
+    pub fn push(&mut self, value: usize) {
+        if self.is_full() {
+            let oldest = self.values.pop_front().unwrap();
+            for &other in self.values.iter().filter(|&&other| other != oldest) {
+                let sum = oldest + other;
+                let count = self.sums.get_mut(&sum).unwrap();
+                *count -= 1;
+                if *count == 0 { self.sums.remove(&sum); }
             }
-            upper += 1;
         }
-        lower += 1;
+        for &other in self.values.iter().filter(|&&other| other != value) {
+            *self.sums.entry(value + other).or_insert(0) += 1;
+        }
+        self.values.push_back(value);
+    }
+
+}
+
+pub struct InvalidValues<I> {
+    numbers: I,
+    window: XmasWindow,
+    position: usize
+}
+
+impl <I: Iterator<Item = usize>> Iterator for InvalidValues<I> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.numbers.next()?;
+            let position = self.position;
+            self.position += 1;
+            if !self.window.check(value) { return Some((position, value)) }
+        }
+    }
+}
+
+pub fn invalid_values<I: IntoIterator<Item = usize>>(numbers: I, preamble: usize) -> Result<InvalidValues<I::IntoIter>, String> {
+    let window = XmasWindow::new(preamble)?;
+    Ok(InvalidValues { numbers: numbers.into_iter(), window, position: 0 })
+}
+
+pub fn read_invalid_values<R: BufRead>(reader: R, preamble: usize) -> Result<impl Iterator<Item = io::Result<(usize, usize)>>, String> {
+    let mut window = XmasWindow::new(preamble)?;
+    Ok(read_numbers(reader)
+        .enumerate()
+        .filter_map(move |(position, value)| match value {
+            Ok(value) if window.check(value) => None,
+            Ok(value) => Some(Ok((position, value))),
+            Err(error) => Some(Err(error))
+        }))
+}
+
+pub fn report_invalid_values<R: BufRead, W: Write>(reader: R, mut writer: W, preamble: usize) -> io::Result<usize> {
+    let invalid = read_invalid_values(reader, preamble)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let mut count = 0;
+    for result in invalid {
+        let (position, value) = result?;
+        writeln!(writer, "line {}: {}", position + 1, value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn find_invalid_values(input: &[usize], preamble: usize) -> Result<Vec<(usize, usize)>, String> {
+    invalid_values(input.iter().copied(), preamble).map(Iterator::collect)
+}
+
+fn find_invalid_value(input: &[usize], preamble: usize) -> usize {
+    match find_invalid_values(input, preamble).unwrap().first() {
+        Some(&(_, value)) => value,
+        None => panic!("No value found")
+    }
+}
+
+pub fn find_contiguous_ranges(input: &[usize], target: usize) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let prefix = input.iter()
+        .scan(0, |sum, value| { *sum += value; Some(*sum) })
+        .fold(vec![0], |mut prefix, sum| { prefix.push(sum); prefix });
+    let mut ranges = Vec::new();
+    let (mut lower, mut upper) = (0, 0);
+    for start in 0..input.len() {
+        let goal = prefix[start] + target;
+        while lower < prefix.len() && prefix[lower] < goal { lower += 1 }
+        upper = upper.max(lower);
+        while upper < prefix.len() && prefix[upper] == goal { upper += 1 }
+        for end in lower.max(start + 2)..upper {
+            ranges.push(start..=end - 1);
+        }
+    }
+    match ranges.is_empty() {
+        true => Err(format!("No contiguous range sums to {}", target)),
+        false => Ok(ranges)
     }
-    panic!("No value found")
+}
+
+pub fn encryption_weakness(input: &[usize], range: &RangeInclusive<usize>) -> usize {
+    let values = &input[range.clone()];
+    values.iter().min().unwrap() + values.iter().max().unwrap()
+}
+
+fn find_continguous(input: &[usize], preamble: usize) -> usize {
+    let target = find_invalid_value(input, preamble);
+    let ranges = find_contiguous_ranges(input, target).unwrap();
+    encryption_weakness(input, &ranges[0])
 }
 
 #[aoc(day9, part1)]
 pub fn solve_part_one(input: &[usize]) -> usize {
-    find_invalid_value(input, 25)
+    find_invalid_value(input, PREAMBLE)
 }
 
 #[aoc(day9, part2)]
 pub fn solve_part_two(input: &[usize]) -> usize {
-    find_continguous(input, 25)
+    find_continguous(input, PREAMBLE)
 }
 
 #[cfg(test)]
@@ -88,6 +201,41 @@ mod test {
         let input = input_generator(INPUT);
         assert_eq!(find_invalid_value(&input, 5), 127);
     }
+
+    #[test]
+    fn it_finds_all_invalid_values() {
+        let mut input = input_generator(INPUT);
+        assert_eq!(find_invalid_values(&input, 5), Ok(vec![(14, 127)]));
+        input.push(1);
+        assert_eq!(find_invalid_values(&input, 5), Ok(vec![(14, 127), (20, 1)]));
+        assert_eq!(find_invalid_values(&[1, 1, 2, 3], 2), Ok(vec![(2, 2)]));
+        assert_eq!(find_invalid_values(&[1, 2, 3], 0), Err("Preamble must contain at least one number".to_owned()));
+        assert!(XmasWindow::new(0).is_err());
+    }
+
+    #[test]
+    fn it_streams_invalid_values() {
+        let mut invalid = read_invalid_values(INPUT.as_bytes(), 5).unwrap();
+        assert_eq!(invalid.next().map(Result::unwrap), Some((14, 127)));
+        assert!(invalid.next().is_none());
+
+        let numbers = read_numbers("1\n2\nthree\n4\n".as_bytes()).collect::<Vec<_>>();
+        assert_eq!(numbers[3].as_ref().unwrap(), &4);
+        assert_eq!(numbers[2].as_ref().unwrap_err().to_string(), "line 3: \"three\" is not a number");
+
+        let invalid = read_invalid_values("1\n2\nx\n9\n11\n".as_bytes(), 2).unwrap().collect::<Vec<_>>();
+        assert_eq!(invalid[0].as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(invalid[1].as_ref().unwrap(), &(3, 9));
+        assert_eq!(invalid.len(), 2);
+
+        let mut output = Vec::new();
+        assert_eq!(report_invalid_values(INPUT.as_bytes(), &mut output, 5).unwrap(), 1);
+        assert_eq!(String::from_utf8(output).unwrap(), "line 15: 127\n");
+        assert!(report_invalid_values("1\n2\nx\n".as_bytes(), Vec::new(), 2).is_err());
+        assert_eq!(report_invalid_values(INPUT.as_bytes(), Vec::new(), 0).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        assert_eq!(invalid_values(vec![3, 4, 7, 100, 11], 2).unwrap().collect::<Vec<_>>(), vec![(3, 100), (4, 11)]);
+    }
     
     #[test]
     fn it_solves_part_two() {
@@ -95,4 +243,17 @@ mod test {
         assert_eq!(find_continguous(&input, 5), 62);
     }
 
+    #[test]
+    fn it_finds_contiguous_ranges() {
+        let input = input_generator(INPUT);
+        assert_eq!(find_contiguous_ranges(&input, 127), Ok(vec![2..=5]));
+        assert_eq!(find_contiguous_ranges(&input, 55), Ok(vec![0..=1]));
+        assert_eq!(find_contiguous_ranges(&[1, 2, 3, 3, 2, 1], 6), Ok(vec![0..=2, 2..=3, 3..=5]));
+        assert_eq!(find_contiguous_ranges(&[0, 5, 0], 5), Ok(vec![0..=1, 0..=2, 1..=2]));
+        assert_eq!(find_contiguous_ranges(&[2, 0, 0, 3, 0], 5), Ok(vec![0..=3, 0..=4]));
+        assert_eq!(find_contiguous_ranges(&[4, 0, 0, 1], 0), Ok(vec![1..=2]));
+        assert_eq!(find_contiguous_ranges(&input, 36), Err("No contiguous range sums to 36".to_string()));
+        assert_eq!(encryption_weakness(&input, &(2..=5)), 62);
+    }
+
 }
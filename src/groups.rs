@@ -0,0 +1,73 @@
+use std::io::{self, BufRead};
+
+pub struct BlankLineGroups<R> {
+    reader: R,
+    buffer: String
+}
+
+impl <R: BufRead> BlankLineGroups<R> {
+    pub fn new(reader: R) -> Self {
+        BlankLineGroups { reader, buffer: String::new() }
+    }
+}
+
+impl <R: BufRead> Iterator for BlankLineGroups<R> {
+    type Item = io::Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut group = Vec::new();
+        loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Err(e) => return Some(Err(e)),
+                Ok(0) if group.is_empty() => return None,
+                Ok(0) => return Some(Ok(group)),
+                Ok(_) => {
+                    let line = self.buffer.trim_end();
+                    match (line.is_empty(), group.is_empty()) {
+                        (true, true) => continue,
+                        (true, false) => return Some(Ok(group)),
+                        (false, _) => group.push(line.to_owned())
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn groups(input: &str) -> impl Iterator<Item = Vec<String>> + '_ {
+    BlankLineGroups::new(input.as_bytes())
+        .map(|group| group.unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn it_splits_on_blank_lines() {
+        let input = indoc! {"
+
+            abc
+            def
+
+            
+            ghi
+        "};
+        assert_eq!(groups(input).collect::<Vec<_>>(), vec![vec!["abc", "def"], vec!["ghi"]]);
+    }
+
+    #[test]
+    fn it_handles_crlf_and_trailing_whitespace() {
+        let input = "ab \r\ncd\t\r\n \r\nef\r\n";
+        assert_eq!(groups(input).collect::<Vec<_>>(), vec![vec!["ab", "cd"], vec!["ef"]]);
+    }
+
+    #[test]
+    fn it_reports_read_errors() {
+        let mut groups = BlankLineGroups::new(&[0x61, 0xff, 0x0a][..]);
+        assert!(groups.next().unwrap().is_err());
+    }
+
+}
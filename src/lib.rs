@@ -21,4 +21,6 @@ pub mod day16;
 pub mod day17;
 pub mod day18;
 
+pub mod groups;
+
 aoc_lib!{ year = 2020 }
\ No newline at end of file